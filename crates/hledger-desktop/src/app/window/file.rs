@@ -57,7 +57,12 @@ pub fn ui(ui: &mut Ui, state: &mut File) {
         let journal_guard = state.watcher.journal();
         let error_guard = state.watcher.error();
         if !error_guard.is_empty() {
-            ui.label("error");
+            for error in error_guard
+                .values()
+                .flat_map(hledger_journal::Error::errors)
+            {
+                ui.label(error.to_string());
+            }
         } else if let Some(journal) = journal_guard.as_ref() {
             transactions_filter_ui(ui, state);
             match state.filtered_transactions_rx.borrow().as_ref() {
@@ -66,7 +71,14 @@ pub fn ui(ui: &mut Ui, state: &mut File) {
                     transactions_list_ui(ui, refs.as_slice());
                 }
                 _ => {
-                    transactions_list_ui(ui, journal.transactions().collect::<Vec<_>>().as_slice());
+                    transactions_list_ui(
+                        ui,
+                        journal
+                            .transactions()
+                            .map(|(_, tx)| tx)
+                            .collect::<Vec<_>>()
+                            .as_slice(),
+                    );
                 }
             }
         } else {
@@ -97,7 +109,7 @@ fn transactions_filter_ui(ui: &mut Ui, state: &mut File) {
             };
             let transactons = journal
                 .transactions()
-                .filter_map(|tx| response.filter(tx))
+                .filter_map(|(_, tx)| response.filter(tx))
                 .collect::<Vec<_>>();
             filtered_transactions_tx.send(Some(transactons)).unwrap();
         }));
@@ -178,7 +190,7 @@ fn posting_ui(ui: &mut Ui, posting: &hledger_journal::Posting) {
         columns[1].with_layout(Layout::right_to_left(Align::Min), |ui| {
             for amount in &posting.amount {
                 ui.label(&amount.commodity);
-                ui.label(amount.quantity.to_string());
+                ui.label(amount.style.format(&amount.quantity));
             }
         });
     });
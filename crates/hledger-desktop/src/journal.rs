@@ -119,28 +119,25 @@ impl InnerWatcher {
         let process_path_task = executor.spawn(async move {
             loop {
                 match paths_receiver.recv().await {
-                    Ok(path) => {
-                        let parsed_journal = hledger_journal::Journal::load(&path).await;
+                    Ok(_) => {
+                        // included files are parsed with the state of the files including them,
+                        // and amounts depend on the whole journal, so any change reloads it all
+                        while paths_receiver.try_recv().is_ok() {}
+                        let parsed_journal = hledger_journal::Journal::load(&path_clone).await;
                         match parsed_journal {
                             Ok(parsed_journal) => {
                                 let mut journal_guard = journal_clone.lock_arc().await;
-                                if path == path_clone {
-                                    let old_paths = journal_guard.as_ref().map(|j| j.includes().collect::<HashSet<_>>()).unwrap_or_default();
-                                    let new_paths = parsed_journal.includes().collect::<HashSet<_>>();
-                                    let to_watch = new_paths.difference(&old_paths).cloned().collect::<Vec<_>>();
-                                    if !to_watch.is_empty() {
-                                        watch_sender.send(WatcherTask::Watch(to_watch)).await.unwrap();
-                                    }
-                                    let to_unwatch = old_paths.difference(&new_paths).cloned().collect::<Vec<_>>();
-                                    if !to_unwatch.is_empty() {
-                                        watch_sender.send(WatcherTask::Unwatch(to_unwatch)).await.unwrap();
-                                    }
+                                let old_paths = journal_guard.as_ref().map(|j| j.includes().collect::<HashSet<_>>()).unwrap_or_default();
+                                let new_paths = parsed_journal.includes().collect::<HashSet<_>>();
+                                let to_watch = new_paths.difference(&old_paths).cloned().collect::<Vec<_>>();
+                                if !to_watch.is_empty() {
+                                    watch_sender.send(WatcherTask::Watch(to_watch)).await.unwrap();
                                 }
-                                if path == path_clone {
-                                    *journal_guard = Some(parsed_journal);
-                                } else if let Some(journal) = journal_guard.as_mut() {
-                                    journal.merge(&parsed_journal);
+                                let to_unwatch = old_paths.difference(&new_paths).cloned().collect::<Vec<_>>();
+                                if !to_unwatch.is_empty() {
+                                    watch_sender.send(WatcherTask::Unwatch(to_unwatch)).await.unwrap();
                                 }
+                                *journal_guard = Some(parsed_journal);
                                 let mut error_guard = error_clone.lock_arc().await;
                                 error_guard.remove(&path_clone);
                             },
@@ -0,0 +1,163 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use hledger_parser::AutosPostingRule;
+use hledger_query::Query;
+
+use crate::{balance, include_chains, Error, Journal};
+
+/// Adds postings generated by auto posting rules to the end of every transaction with a posting
+/// matching the rule's query. Generated postings are not matched again.
+///
+/// Rules are applied once amounts are resolved, so they match inferred amounts too. Transactions
+/// are balanced again with their generated postings, those that do not balance are kept as they
+/// are when `lenient` is set.
+pub(crate) fn apply(journal: &mut Journal, lenient: bool) -> Result<(), Error> {
+    let rules = journal
+        .auto_postings()
+        .map(|(path, rule)| {
+            Query::from_terms(&rule.query)
+                .map(|query| (path.to_path_buf(), query, rule.clone()))
+                .map_err(|error| Error::Query(Arc::new(error)))
+        })
+        .collect::<Result<Vec<(PathBuf, Query, AutosPostingRule)>, _>>()?;
+    if rules.is_empty() {
+        return Ok(());
+    }
+
+    let chains = include_chains(journal);
+    let mut errors = Vec::new();
+    for (path, transaction) in balance::ordered(journal) {
+        let in_file = |error| Error::in_file(&chains, path, error);
+        let mut generated = Vec::new();
+        for posting in &transaction.postings {
+            for (rule_path, query, rule) in &rules {
+                if !query.matches(transaction, posting) {
+                    continue;
+                }
+                // like in hledger, balance assignments can not be matched: they were resolved
+                // without the postings generated for earlier transactions
+                if !posting.is_amount_specified && posting.assertion.is_some() {
+                    errors.push(in_file(Error::AutoPostingsOnAssignment {
+                        position: transaction.position.clone(),
+                        account: posting.account_name.to_string(),
+                    }));
+                    continue;
+                }
+                match rule.evaluate(posting) {
+                    Some(postings) => generated.extend(postings),
+                    None => errors.push(in_file(Error::AutoPostingAmount {
+                        position: transaction.position.clone(),
+                        rule: (rule_path.clone(), rule.position.clone()),
+                    })),
+                }
+            }
+        }
+        if generated.is_empty() {
+            continue;
+        }
+        transaction.postings.extend(generated);
+        match balance::balance(transaction) {
+            Err(Error::UnbalancedTransaction { .. } | Error::TooManyMissingAmounts { .. })
+                if lenient => {}
+            Err(error) => errors.push(in_file(error)),
+            Ok(_) => {}
+        }
+    }
+    match Error::from_errors(errors) {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{in_file, Amount};
+
+    // journal with its amounts resolved and auto postings applied
+    fn apply_to(contents: &str) -> Result<Journal, Error> {
+        let mut journal = Journal::from_contents(contents);
+        balance::resolve(&mut journal, false)?;
+        apply(&mut journal, false)?;
+        Ok(journal)
+    }
+
+    #[test]
+    fn generated_postings() {
+        let journal = apply_to(
+            "= expenses:food
+    (budget:food)  *-1
+    [assets:reserved]  $1
+    [assets:cash]  $-1
+
+2024-01-01 lunch
+    expenses:food  $10
+    assets:cash",
+        )
+        .unwrap();
+        let (_, transaction) = journal.transactions().next().unwrap();
+        assert_eq!(transaction.postings.len(), 5);
+        assert_eq!(
+            transaction
+                .postings
+                .iter()
+                .map(|posting| posting.is_generated)
+                .collect::<Vec<_>>(),
+            vec![false, false, true, true, true]
+        );
+        assert_eq!(
+            transaction.postings[2].amount,
+            vec![Amount {
+                quantity: rust_decimal::Decimal::new(-10, 0),
+                commodity: String::from("$"),
+                ..Amount::default()
+            }]
+        );
+    }
+
+    #[test]
+    fn inferred_amounts() {
+        let journal = apply_to(
+            "= assets:cash\n    (budget)  *-1\n    (total)  *1\n\n2024-01-01 lunch\n    expenses:food  $10\n    assets:cash",
+        )
+        .unwrap();
+        let (_, transaction) = journal.transactions().next().unwrap();
+        assert_eq!(
+            transaction.postings[2].amount,
+            vec![Amount {
+                quantity: rust_decimal::Decimal::new(10, 0),
+                commodity: String::from("$"),
+                ..Amount::default()
+            }]
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let error = apply_to(
+            "= expenses:food\n    (budget)  *-1\n    (total)  *1\n\n2024-01-01 lunch\n    expenses:food  = $10\n    assets:cash",
+        )
+        .unwrap_err();
+        assert!(matches!(
+            in_file(&error).1,
+            Error::AutoPostingsOnAssignment { account, .. } if account == "expenses:food"
+        ));
+
+        let error = apply_to(
+            "= expenses:food\n    [budget]  $1\n    [total]  $-2\n\n= assets\n    (budget)  (amount * $2)\n    (total)  *1\n\n2024-01-01 lunch\n    expenses:food  $10\n    assets:cash",
+        )
+        .unwrap_err();
+        let errors = error.errors().collect::<Vec<_>>();
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(
+            in_file(errors[0]),
+            (path, Error::AutoPostingAmount { rule: (rule_path, rule), .. })
+                if rule_path == path && rule.start == 51
+        ));
+        assert!(matches!(
+            in_file(errors[1]).1,
+            Error::UnbalancedTransaction { .. }
+        ));
+    }
+}
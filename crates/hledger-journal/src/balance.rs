@@ -0,0 +1,647 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use hledger_parser::{AmountPrice, Directive};
+use rust_decimal::Decimal;
+
+use crate::{include_chains, Amount, Error, Journal, Transaction};
+
+/// Running balances of accounts, per commodity.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Balances(HashMap<String, HashMap<String, Decimal>>);
+
+impl Balances {
+    /// Adds the amount to the balance of the account, returning `None` when it overflows.
+    pub fn add(&mut self, account: &str, amount: &Amount) -> Option<()> {
+        let balance = self
+            .0
+            .entry(account.to_string())
+            .or_default()
+            .entry(amount.commodity.clone())
+            .or_default();
+        *balance = balance.checked_add(amount.quantity)?;
+        Some(())
+    }
+
+    /// Returns balance of the account in every commodity, including its subaccounts when
+    /// `inclusive` is set, or `None` when it overflows.
+    pub fn get(&self, account: &str, inclusive: bool) -> Option<HashMap<String, Decimal>> {
+        let subaccount_prefix = format!("{account}:");
+        let mut result = HashMap::<String, Decimal>::new();
+        for (name, balance) in &self.0 {
+            if name == account || (inclusive && name.starts_with(&subaccount_prefix)) {
+                for (commodity, quantity) in balance {
+                    let sum = result.entry(commodity.clone()).or_default();
+                    *sum = sum.checked_add(*quantity)?;
+                }
+            }
+        }
+        Some(result)
+    }
+}
+
+fn collect<'a>(journal: &'a mut Journal, transactions: &mut Vec<(&'a Path, &'a mut Transaction)>) {
+    let path = journal.path.as_path();
+    let mut includes = journal.includes.iter_mut().peekable();
+    for (index, directive) in journal.directives.iter_mut().enumerate() {
+        if let Directive::Transaction(transaction) = directive {
+            transactions.push((path, transaction));
+        }
+        while let Some((_, included)) = includes.next_if(|(at, _)| *at == index) {
+            collect(included, transactions);
+        }
+    }
+}
+
+/// Returns all transactions of the journal and its includes, with the file they came from, in
+/// the order of [`Journal::transactions_by_date`].
+pub(crate) fn ordered(journal: &mut Journal) -> Vec<(&Path, &mut Transaction)> {
+    let mut transactions = Vec::new();
+    collect(journal, &mut transactions);
+    transactions.sort_by_key(|(_, transaction)| transaction.date);
+    transactions
+}
+
+// Sum of a commodity in a transaction. It balances when it is zero at the precision its amounts
+// are written with. Costs are often more precise than the amounts they balance, so their precision
+// only counts when the commodity is not written otherwise.
+struct Sum {
+    amount: Amount,
+    precision: Option<u32>,
+    cost_precision: u32,
+}
+
+impl Sum {
+    fn is_zero(&self) -> bool {
+        self.amount
+            .quantity
+            .round_dp(self.precision.unwrap_or(self.cost_precision))
+            .is_zero()
+    }
+}
+
+// returns `None` when the cost or the sum overflows
+fn add(sums: &mut Vec<Sum>, amount: &Amount) -> Option<()> {
+    let (cost, precision, cost_precision) = match amount.price.as_deref() {
+        Some(AmountPrice::Unit(price) | AmountPrice::Total(price)) => {
+            (amount.cost()?, None, price.quantity.scale())
+        }
+        None => (amount.clone(), Some(amount.quantity.scale()), 0),
+    };
+    match sums
+        .iter_mut()
+        .find(|sum| sum.amount.commodity == cost.commodity)
+    {
+        Some(sum) => {
+            sum.amount.quantity = sum.amount.quantity.checked_add(cost.quantity)?;
+            sum.precision = sum.precision.max(precision);
+            sum.cost_precision = sum.cost_precision.max(cost_precision);
+        }
+        None => sums.push(Sum {
+            amount: Amount {
+                price: None,
+                ..cost
+            },
+            precision,
+            cost_precision,
+        }),
+    }
+    Some(())
+}
+
+// Gives the amounts of the first commodity a total price in the second one, so that they balance,
+// like hledger does for transactions converting between two commodities without a written cost.
+// The last amount gets what is left, so that rounding does not unbalance the transaction. Returns
+// whether costs were inferred, which fails only when they overflow.
+fn infer_costs(
+    transaction: &mut Transaction,
+    postings: &[usize],
+    from: &Amount,
+    to: &Amount,
+) -> bool {
+    let amounts = postings
+        .iter()
+        .flat_map(|index| (0..transaction.postings[*index].amount.len()).map(|i| (*index, i)))
+        .filter(|(index, i)| transaction.postings[*index].amount[*i].commodity == from.commodity)
+        .collect::<Vec<_>>();
+    let mut left = -to.quantity;
+    let mut costs = Vec::with_capacity(amounts.len());
+    for (n, (index, i)) in amounts.iter().enumerate() {
+        let cost = if n + 1 == amounts.len() {
+            left
+        } else {
+            let Some(cost) = to
+                .quantity
+                .checked_mul(transaction.postings[*index].amount[*i].quantity)
+                .and_then(|quantity| quantity.checked_div(from.quantity))
+            else {
+                return false;
+            };
+            -cost
+        };
+        let Some(rest) = left.checked_sub(cost) else {
+            return false;
+        };
+        left = rest;
+        costs.push(cost);
+    }
+    for ((index, i), cost) in amounts.into_iter().zip(costs) {
+        transaction.postings[index].amount[i].price = Some(Box::new(AmountPrice::Total(Amount {
+            quantity: cost.abs(),
+            price: None,
+            ..to.clone()
+        })));
+    }
+    true
+}
+
+/// Infers the amount of the posting without one, separately for real and balanced virtual
+/// postings, and checks that both sum to zero. Returns indices of the inferred postings.
+///
+/// When all amounts are written, none has a cost and they are in exactly two commodities, the
+/// amounts in the first commodity get the cost that balances the transaction.
+pub(crate) fn balance(transaction: &mut Transaction) -> Result<Vec<usize>, Error> {
+    let mut inferred = Vec::new();
+    for is_balanced_virtual in [false, true] {
+        // sums per commodity, in order of appearance
+        let mut sums = Vec::new();
+        let mut missing = Vec::new();
+        let mut specified = Vec::new();
+        let postings = transaction
+            .postings
+            .iter()
+            .enumerate()
+            .filter(|(_, posting)| {
+                !posting.is_virtual && posting.is_balanced_virtual == is_balanced_virtual
+            });
+        for (index, posting) in postings {
+            // inferred amounts count as written, so that transactions can be balanced again
+            if posting.amount.is_empty() && posting.assertion.is_none() {
+                missing.push(index);
+                continue;
+            }
+            specified.push(index);
+            for amount in &posting.amount {
+                add(&mut sums, amount).ok_or_else(|| Error::Overflow {
+                    position: transaction.position.clone(),
+                })?;
+            }
+        }
+        let has_costs = specified.iter().any(|index| {
+            transaction.postings[*index]
+                .amount
+                .iter()
+                .any(|amount| amount.price.is_some())
+        });
+        let sum_count = sums.len();
+        let mut unbalanced = sums
+            .into_iter()
+            .filter(|sum| !sum.is_zero())
+            .map(|sum| sum.amount)
+            .collect::<Vec<_>>();
+        if let [from, to] = unbalanced.as_slice() {
+            if missing.is_empty()
+                && !has_costs
+                && sum_count == 2
+                && infer_costs(transaction, &specified, from, to)
+            {
+                unbalanced.clear();
+            }
+        }
+        match missing.as_slice() {
+            [] if unbalanced.is_empty() => {}
+            [] => {
+                return Err(Error::UnbalancedTransaction {
+                    position: transaction.position.clone(),
+                    amounts: unbalanced,
+                })
+            }
+            [index] => {
+                transaction.postings[*index].amount = if unbalanced.is_empty() {
+                    vec![Amount::default()]
+                } else {
+                    unbalanced
+                        .into_iter()
+                        .map(|amount| Amount {
+                            quantity: -amount.quantity,
+                            ..amount
+                        })
+                        .collect()
+                };
+                inferred.push(*index);
+            }
+            _ => {
+                return Err(Error::TooManyMissingAmounts {
+                    position: transaction.position.clone(),
+                })
+            }
+        }
+    }
+    Ok(inferred)
+}
+
+/// Fills in amounts that are not written in the journal, processing transactions in date order,
+/// and checks that transactions balance.
+///
+/// Balance assignments, postings without an amount but with an assertion, get the amount that
+/// makes the account balance the asserted one. Then the single posting without an amount of a
+/// transaction gets the amount that balances it. Every transaction that can not be balanced is
+/// reported, unless `lenient` is set, which keeps it as it is.
+pub(crate) fn resolve(journal: &mut Journal, lenient: bool) -> Result<(), Error> {
+    let chains = include_chains(journal);
+    let mut balances = Balances::default();
+    let mut errors = Vec::new();
+    for (path, transaction) in ordered(journal) {
+        let overflow = Error::Overflow {
+            position: transaction.position.clone(),
+        };
+        if let Err(error) = assign(&mut balances, transaction) {
+            errors.push(Error::in_file(&chains, path, error));
+            continue;
+        }
+        let inferred = match balance(transaction) {
+            Ok(inferred) => inferred,
+            Err(Error::UnbalancedTransaction { .. } | Error::TooManyMissingAmounts { .. })
+                if lenient =>
+            {
+                continue;
+            }
+            Err(error) => {
+                errors.push(Error::in_file(&chains, path, error));
+                continue;
+            }
+        };
+        let added = inferred.into_iter().all(|index| {
+            let posting = &transaction.postings[index];
+            let account = posting.account_name.to_string();
+            posting
+                .amount
+                .iter()
+                .all(|amount| balances.add(&account, amount).is_some())
+        });
+        // so that assertions can be checked without overflowing
+        let asserted = transaction.postings.iter().all(|posting| {
+            posting.assertion.as_ref().map_or(true, |assertion| {
+                let account = posting.account_name.to_string();
+                balances
+                    .get(&account, assertion.is_subaccount_inclusive)
+                    .is_some()
+            })
+        });
+        if !added || !asserted {
+            errors.push(Error::in_file(&chains, path, overflow));
+        }
+    }
+    match Error::from_errors(errors) {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+// gives balance assignments their amount and adds the written amounts to the running balances,
+// failing when they overflow
+fn assign(balances: &mut Balances, transaction: &mut Transaction) -> Result<(), Error> {
+    let overflow = || Error::Overflow {
+        position: transaction.position.clone(),
+    };
+    for posting in &mut transaction.postings {
+        let account = posting.account_name.to_string();
+        if let Some(assertion) = posting
+            .assertion
+            .as_ref()
+            .filter(|_| !posting.is_amount_specified)
+        {
+            let balance = balances
+                .get(&account, assertion.is_subaccount_inclusive)
+                .ok_or_else(overflow)?;
+            let current = balance
+                .get(&assertion.amount.commodity)
+                .copied()
+                .unwrap_or_default();
+            let mut amounts = vec![Amount {
+                quantity: assertion
+                    .amount
+                    .quantity
+                    .checked_sub(current)
+                    .ok_or_else(overflow)?,
+                ..assertion.amount.clone()
+            }];
+            // strict assignments also clear balances in other commodities
+            if assertion.is_strict {
+                let mut others = balance
+                    .into_iter()
+                    .filter(|(commodity, quantity)| {
+                        *commodity != assertion.amount.commodity && !quantity.is_zero()
+                    })
+                    .collect::<Vec<_>>();
+                others.sort();
+                amounts.extend(others.into_iter().map(|(commodity, quantity)| Amount {
+                    quantity: -quantity,
+                    commodity,
+                    ..Amount::default()
+                }));
+            }
+            posting.amount = amounts;
+        }
+        for amount in &posting.amount {
+            balances.add(&account, amount).ok_or_else(overflow)?;
+        }
+    }
+    Ok(())
+}
+
+/// Balance assertion that does not hold.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+#[error(
+    "balance assertion failed at {}:{}: {account} is expected to be {} {} but is {} {}",
+    path.display(),
+    posting_position.start,
+    expected.quantity,
+    expected.commodity,
+    actual.quantity,
+    actual.commodity
+)]
+pub struct AssertionFailure {
+    pub path: std::path::PathBuf,
+    /// Position of the transaction with the assertion.
+    pub position: std::ops::Range<usize>,
+    /// Index of the posting with the assertion in its transaction.
+    pub posting: usize,
+    /// Position of the posting with the assertion.
+    pub posting_position: std::ops::Range<usize>,
+    pub account: String,
+    pub expected: Amount,
+    pub actual: Amount,
+}
+
+/// Checks balance assertions, processing postings in date order.
+///
+/// An assertion checks the balance of the asserted commodity after its posting, including
+/// subaccounts for `=*` and `==*`. Strict assertions, `==` and `==*`, also check that balances
+/// in every other commodity are zero.
+///
+/// Balances too large to compute are reported when loading, their assertions are skipped.
+pub(crate) fn check_assertions(journal: &Journal) -> Vec<AssertionFailure> {
+    let mut balances = Balances::default();
+    let mut failures = Vec::new();
+    for (path, transaction) in journal.transactions_by_date() {
+        for (index, posting) in transaction.postings.iter().enumerate() {
+            let account = posting.account_name.to_string();
+            for amount in &posting.amount {
+                balances.add(&account, amount);
+            }
+            let Some(assertion) = posting.assertion.as_ref() else {
+                continue;
+            };
+            let Some(balance) = balances.get(&account, assertion.is_subaccount_inclusive) else {
+                continue;
+            };
+            let mut expected = vec![assertion.amount.clone()];
+            if assertion.is_strict {
+                let mut others = balance
+                    .keys()
+                    .filter(|commodity| **commodity != assertion.amount.commodity)
+                    .collect::<Vec<_>>();
+                others.sort();
+                expected.extend(others.into_iter().map(|commodity| Amount {
+                    commodity: commodity.clone(),
+                    ..Amount::default()
+                }));
+            }
+            for expected in expected {
+                let actual = balance
+                    .get(&expected.commodity)
+                    .copied()
+                    .unwrap_or_default();
+                // like in hledger, amounts are compared exactly, whatever precision they are
+                // written with
+                if actual != expected.quantity {
+                    failures.push(AssertionFailure {
+                        path: path.to_path_buf(),
+                        position: transaction.position.clone(),
+                        posting: index,
+                        posting_position: posting.position.clone(),
+                        account: account.clone(),
+                        actual: Amount {
+                            quantity: actual,
+                            commodity: expected.commodity.clone(),
+                            ..Amount::default()
+                        },
+                        expected,
+                    });
+                }
+            }
+        }
+    }
+    failures
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::in_file;
+
+    fn transaction(text: &str) -> Transaction {
+        let directives = hledger_parser::parse(text).unwrap();
+        match directives.into_iter().next() {
+            Some(Directive::Transaction(transaction)) => transaction,
+            _ => panic!("not a transaction"),
+        }
+    }
+
+    #[test]
+    fn cost_overflow() {
+        let mut transaction = transaction(
+            "2024-01-01 test
+    a  79228162514264337593543950335 A @ $2
+    b",
+        );
+        assert!(matches!(
+            balance(&mut transaction),
+            Err(Error::Overflow { .. })
+        ));
+    }
+
+    #[test]
+    fn balance_overflow() {
+        let mut journal = Journal::from_contents(
+            "2024-01-01 first
+    a  $79228162514264337593543950335
+    b
+
+2024-01-02 second
+    a  $79228162514264337593543950335
+    b",
+        );
+        let error = resolve(&mut journal, false).unwrap_err();
+        assert!(matches!(
+            error,
+            Error::File { error, .. } if matches!(*error, Error::Overflow { ref position } if position.start == 62)
+        ));
+    }
+
+    #[test]
+    fn balance_assignment() {
+        let mut journal = Journal::from_contents(
+            "2024-01-02 adjust
+    assets:cash  = $50
+    equity
+
+2024-01-01 salary
+    assets:cash  $100
+    income
+
+2024-01-03 reset
+    assets  ==* 10 EUR
+    equity",
+        );
+        resolve(&mut journal, false).unwrap();
+        let amounts = journal
+            .transactions()
+            .map(|(_, tx)| tx.postings[0].amount.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            amounts[0],
+            vec![Amount {
+                quantity: Decimal::new(-50, 0),
+                commodity: String::from("$"),
+                ..Amount::default()
+            }]
+        );
+        assert_eq!(
+            amounts[2],
+            vec![
+                Amount {
+                    quantity: Decimal::new(10, 0),
+                    commodity: String::from("EUR"),
+                    ..Amount::default()
+                },
+                Amount {
+                    quantity: Decimal::new(-50, 0),
+                    commodity: String::from("$"),
+                    ..Amount::default()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn infer_missing_amount() {
+        let mut transaction = transaction(
+            "2024-01-01 buy
+    assets:stock  3 AAPL @ $1.333
+    [budget:stock]  $-4
+    [budget:free]
+    assets:cash  $-3.999
+    fees  EUR 1
+    equity",
+        );
+        assert_eq!(balance(&mut transaction).unwrap(), vec![5, 2]);
+        assert_eq!(
+            transaction.postings[2].amount,
+            vec![Amount {
+                quantity: Decimal::new(4, 0),
+                commodity: String::from("$"),
+                ..Amount::default()
+            }]
+        );
+        assert!(!transaction.postings[2].is_amount_specified);
+        assert_eq!(
+            transaction.postings[5].amount,
+            vec![Amount {
+                quantity: Decimal::new(-1, 0),
+                commodity: String::from("EUR"),
+                ..Amount::default()
+            }]
+        );
+    }
+
+    #[test]
+    fn implicit_cost() {
+        let costs = |text| {
+            let mut transaction = transaction(text);
+            balance(&mut transaction).unwrap();
+            transaction
+                .postings
+                .iter()
+                .map(|posting| posting.amount[0].cost().map(|cost| cost.quantity))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            costs("2024-01-01 exchange\n    assets:eur  10 EUR\n    assets:cash  $-11"),
+            vec![Some(Decimal::new(11, 0)), None]
+        );
+        assert_eq!(
+            costs("2024-01-02 split\n    assets:eur  1 EUR\n    assets:eur  2 EUR\n    assets:cash  $-3.33"),
+            vec![Some(Decimal::new(111, 2)), Some(Decimal::new(222, 2)), None]
+        );
+    }
+
+    #[test]
+    fn unbalanced_transaction() {
+        let mut journal = Journal::from_contents(
+            "2024-01-01 ok\n    a  3 X @ $0.3333\n    b  $-1.00\n\n2024-01-02 bad\n    a  $1\n    b  $-2\n\n2024-01-03 worse\n    a  $1\n    b\n    c",
+        );
+        let error = resolve(&mut journal, false).unwrap_err();
+        let errors = error.errors().collect::<Vec<_>>();
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(
+            in_file(errors[0]).1,
+            Error::UnbalancedTransaction { position, amounts }
+                if *position == (50..85) && amounts[0].quantity == Decimal::new(-1, 0)
+        ));
+        assert!(matches!(
+            in_file(errors[1]).1,
+            Error::TooManyMissingAmounts { .. }
+        ));
+    }
+
+    #[test]
+    fn assertions() {
+        let mut journal = Journal::from_contents(
+            "2024-01-02 check
+    assets:cash  $0 = $100
+    assets  $0 =* $110.00
+    assets:cash  1 EUR == $100
+    equity
+
+2024-01-01 deposit
+    assets:cash  $100
+    assets:bank  $10
+    income",
+        );
+        resolve(&mut journal, false).unwrap();
+        assert_eq!(
+            check_assertions(&journal),
+            vec![AssertionFailure {
+                path: std::path::PathBuf::from("main.journal"),
+                position: 0..111,
+                posting: 2,
+                posting_position: 70..100,
+                account: String::from("assets:cash"),
+                expected: Amount {
+                    commodity: String::from("EUR"),
+                    ..Amount::default()
+                },
+                actual: Amount {
+                    quantity: Decimal::new(1, 0),
+                    commodity: String::from("EUR"),
+                    ..Amount::default()
+                },
+            }]
+        );
+
+        let mut journal =
+            Journal::from_contents("2024-01-01 deposit\n    assets  $1 = $1\n    income = $-1");
+        resolve(&mut journal, false).unwrap();
+        assert!(check_assertions(&journal).is_empty());
+
+        let journal = Journal::from_contents(
+            "2024-01-01 deposit\n    assets  $50.40 = $50\n    income  $-50.40 = $-50.40",
+        );
+        let failures = check_assertions(&journal);
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].account, "assets");
+        assert_eq!(failures[0].actual.quantity, Decimal::new(5040, 2));
+    }
+}
@@ -0,0 +1,475 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use hledger_parser::Commodity;
+
+use crate::{balance, Error, Journal, Posting, Transaction};
+
+/// Validations of `hledger check`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Check {
+    /// Accounts of postings are declared.
+    Accounts,
+    /// Commodities of amounts and costs are declared.
+    Commodities,
+    /// Payees of transactions are declared.
+    Payees,
+    /// Tags of transactions and postings are declared.
+    Tags,
+    /// Transactions of each file are in date order.
+    OrderedDates,
+    /// No two accounts have the same last name part.
+    UniqueLeafNames,
+    /// Transactions balance. Loading fails on those that do not, unless it is lenient.
+    Balanced,
+    /// Balance assertions hold.
+    Assertions,
+    /// Accounts with balance assertions have one at most 7 days before their latest posting.
+    RecentAssertions,
+}
+
+impl Check {
+    /// Checks of `hledger check strict`.
+    pub const STRICT: [Check; 4] = [
+        Check::Balanced,
+        Check::Assertions,
+        Check::Accounts,
+        Check::Commodities,
+    ];
+
+    /// Every check.
+    pub const ALL: [Check; 9] = [
+        Check::Accounts,
+        Check::Commodities,
+        Check::Payees,
+        Check::Tags,
+        Check::OrderedDates,
+        Check::UniqueLeafNames,
+        Check::Balanced,
+        Check::Assertions,
+        Check::RecentAssertions,
+    ];
+}
+
+/// Problem found by a check, pointing at the transaction it is about, and at the posting when
+/// it is about one.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+#[error("{}:{}: {message}", path.display(), posting.as_ref().unwrap_or(position).start)]
+pub struct Diagnostic {
+    pub check: Check,
+    pub path: PathBuf,
+    pub position: std::ops::Range<usize>,
+    /// Position of the posting the problem is about.
+    pub posting: Option<std::ops::Range<usize>>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn new(
+        check: Check,
+        path: &Path,
+        transaction: &Transaction,
+        posting: Option<&Posting>,
+        message: String,
+    ) -> Self {
+        Self {
+            check,
+            path: path.to_path_buf(),
+            position: transaction.position.clone(),
+            posting: posting.map(|posting| posting.position.clone()),
+            message,
+        }
+    }
+}
+
+/// Runs the checks on the journal and its includes, in order.
+pub(crate) fn run(journal: &Journal, checks: &[Check]) -> Vec<Diagnostic> {
+    checks
+        .iter()
+        .flat_map(|check| match check {
+            Check::Accounts => accounts(journal),
+            Check::Commodities => commodities(journal),
+            Check::Payees => payees(journal),
+            Check::Tags => tags(journal),
+            Check::OrderedDates => ordered_dates(journal),
+            Check::UniqueLeafNames => unique_leaf_names(journal),
+            Check::Balanced => balanced(journal),
+            Check::Assertions => assertions(journal),
+            Check::RecentAssertions => recent_assertions(journal),
+        })
+        .collect()
+}
+
+// reports the first use of every name that is not declared, with the posting using it if the
+// transaction does not
+fn undeclared<'a>(
+    journal: &'a Journal,
+    check: Check,
+    declared: &HashSet<String>,
+    used: impl Fn(&'a Transaction) -> Vec<(Option<&'a Posting>, String)>,
+    message: impl Fn(&str) -> String,
+) -> Vec<Diagnostic> {
+    let mut reported = HashSet::new();
+    let mut diagnostics = Vec::new();
+    for (path, transaction) in journal.transactions() {
+        for (posting, name) in used(transaction) {
+            if !declared.contains(&name) && reported.insert(name.clone()) {
+                diagnostics.push(Diagnostic::new(
+                    check,
+                    path,
+                    transaction,
+                    posting,
+                    message(&name),
+                ));
+            }
+        }
+    }
+    diagnostics
+}
+
+fn accounts(journal: &Journal) -> Vec<Diagnostic> {
+    let declared = journal
+        .accounts()
+        .map(|(_, account)| account.account_name.to_string())
+        .collect();
+    undeclared(
+        journal,
+        Check::Accounts,
+        &declared,
+        |transaction| {
+            transaction
+                .postings
+                .iter()
+                .map(|posting| (Some(posting), posting.account_name.to_string()))
+                .collect()
+        },
+        |account| format!("account {account} is not declared"),
+    )
+}
+
+fn commodities(journal: &Journal) -> Vec<Diagnostic> {
+    let declared = journal
+        .commodities()
+        .map(|(_, commodity)| match commodity {
+            Commodity::Amount(amount) => amount.commodity.clone(),
+            Commodity::Commodity(commodity) => commodity.clone(),
+        })
+        .collect();
+    undeclared(
+        journal,
+        Check::Commodities,
+        &declared,
+        |transaction| {
+            transaction
+                .postings
+                .iter()
+                .flat_map(|posting| {
+                    posting
+                        .amount
+                        .iter()
+                        .flat_map(|amount| std::iter::once(amount.clone()).chain(amount.cost()))
+                        .map(move |amount| (Some(posting), amount.commodity))
+                })
+                .filter(|(_, commodity)| !commodity.is_empty())
+                .collect()
+        },
+        |commodity| format!("commodity {commodity} is not declared"),
+    )
+}
+
+fn payees(journal: &Journal) -> Vec<Diagnostic> {
+    let declared = journal
+        .payees()
+        .map(|(_, payee)| payee.name.clone())
+        .collect();
+    undeclared(
+        journal,
+        Check::Payees,
+        &declared,
+        |transaction| {
+            Some(transaction.payee.clone())
+                .filter(|payee| !payee.is_empty())
+                .map(|payee| (None, payee))
+                .into_iter()
+                .collect()
+        },
+        |payee| format!("payee {payee} is not declared"),
+    )
+}
+
+fn tags(journal: &Journal) -> Vec<Diagnostic> {
+    let declared = journal.tags().map(|(_, tag)| tag.name.clone()).collect();
+    undeclared(
+        journal,
+        Check::Tags,
+        &declared,
+        |transaction| {
+            transaction
+                .tags
+                .iter()
+                .map(|(name, _)| (None, name.clone()))
+                .chain(transaction.postings.iter().flat_map(|posting| {
+                    posting
+                        .tags
+                        .iter()
+                        .map(move |(name, _)| (Some(posting), name.clone()))
+                }))
+                .collect()
+        },
+        |tag| format!("tag {tag} is not declared"),
+    )
+}
+
+fn ordered_dates(journal: &Journal) -> Vec<Diagnostic> {
+    let mut latest = HashMap::<&Path, chrono::NaiveDate>::new();
+    let mut diagnostics = Vec::new();
+    for (path, transaction) in journal.transactions() {
+        match latest.get(path) {
+            Some(date) if transaction.date < *date => {
+                diagnostics.push(Diagnostic::new(
+                    Check::OrderedDates,
+                    path,
+                    transaction,
+                    None,
+                    format!(
+                        "transaction date {} is before the previous transaction date {date}",
+                        transaction.date
+                    ),
+                ));
+            }
+            _ => {
+                latest.insert(path, transaction.date);
+            }
+        }
+    }
+    diagnostics
+}
+
+fn unique_leaf_names(journal: &Journal) -> Vec<Diagnostic> {
+    // full names of every leaf name, in order of first use
+    let mut accounts = BTreeMap::<String, Vec<String>>::new();
+    let mut diagnostics = Vec::new();
+    for (path, transaction) in journal.transactions() {
+        for posting in &transaction.postings {
+            let account = posting.account_name.to_string();
+            let leaf = account.rsplit(':').next().unwrap_or_default().to_string();
+            let names = accounts.entry(leaf.clone()).or_default();
+            if names.contains(&account) {
+                continue;
+            }
+            names.push(account);
+            if names.len() > 1 {
+                diagnostics.push(Diagnostic::new(
+                    Check::UniqueLeafNames,
+                    path,
+                    transaction,
+                    Some(posting),
+                    format!(
+                        "account leaf name {leaf} is not unique, it is used by {}",
+                        names.join(", ")
+                    ),
+                ));
+            }
+        }
+    }
+    diagnostics
+}
+
+fn balanced(journal: &Journal) -> Vec<Diagnostic> {
+    journal
+        .transactions()
+        .filter_map(|(path, transaction)| {
+            // amounts are resolved when loading, so balancing again only fails on transactions
+            // kept as they are by a lenient load
+            let message = match balance::balance(&mut transaction.clone()).err()? {
+                Error::UnbalancedTransaction { amounts, .. } => format!(
+                    "transaction does not balance, its amounts sum to {}",
+                    amounts
+                        .iter()
+                        .map(|amount| format!("{} {}", amount.quantity, amount.commodity))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+                Error::TooManyMissingAmounts { .. } => {
+                    String::from("transaction has more than one posting without an amount")
+                }
+                _ => String::from("amounts of the transaction are too large to add up"),
+            };
+            Some(Diagnostic::new(
+                Check::Balanced,
+                path,
+                transaction,
+                None,
+                message,
+            ))
+        })
+        .collect()
+}
+
+fn assertions(journal: &Journal) -> Vec<Diagnostic> {
+    balance::check_assertions(journal)
+        .into_iter()
+        .map(|failure| Diagnostic {
+            check: Check::Assertions,
+            path: failure.path.clone(),
+            position: failure.position.clone(),
+            posting: Some(failure.posting_position.clone()),
+            message: format!(
+                "balance assertion of {} expected {} {} but is {} {}",
+                failure.account,
+                failure.expected.quantity,
+                failure.expected.commodity,
+                failure.actual.quantity,
+                failure.actual.commodity
+            ),
+        })
+        .collect()
+}
+
+fn recent_assertions(journal: &Journal) -> Vec<Diagnostic> {
+    // latest assertion and latest posting of every account, by date
+    let mut latest_assertion = BTreeMap::<String, chrono::NaiveDate>::new();
+    let mut latest_posting = BTreeMap::<String, (&Path, &Transaction, &Posting)>::new();
+    for (path, transaction) in journal.transactions_by_date() {
+        for posting in &transaction.postings {
+            let account = posting.account_name.to_string();
+            if posting.assertion.is_some() {
+                latest_assertion.insert(account.clone(), transaction.date);
+            }
+            latest_posting.insert(account, (path, transaction, posting));
+        }
+    }
+    latest_assertion
+        .into_iter()
+        .filter_map(|(account, assertion)| {
+            let (path, transaction, posting) = latest_posting[&account];
+            let days = (transaction.date - assertion).num_days();
+            (days > 7).then(|| {
+                Diagnostic::new(
+                    Check::RecentAssertions,
+                    path,
+                    transaction,
+                    Some(posting),
+                    format!(
+                        "the latest balance assertion of {account} is {days} days before its latest posting"
+                    ),
+                )
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn balanced() {
+        let mut journal = Journal::from_contents(
+            "2024-01-01 unbalanced
+    assets:cash  $-1
+    expenses:food  $2
+
+2024-01-02 missing
+    assets:cash  $-1
+    expenses:food
+    income:cash
+
+2024-01-03 balanced
+    assets:cash  $-1
+    expenses:food
+",
+        );
+        balance::resolve(&mut journal, true).unwrap();
+        let diagnostics = run(&journal, &Check::STRICT);
+        let balanced = diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.check == Check::Balanced)
+            .map(|diagnostic| (diagnostic.position.start, diagnostic.message.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            balanced,
+            vec![
+                (0, "transaction does not balance, its amounts sum to 1 $"),
+                (
+                    66,
+                    "transaction has more than one posting without an amount"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn checks() {
+        let mut journal = Journal::from_contents(
+            "account assets:cash
+commodity $1.00
+payee Shop
+tag trip
+
+2024-01-05 Shop  ; trip: paris
+    assets:cash  $-1 = $-1
+    expenses:food  $1  ; type: groceries
+
+2024-01-01 Other
+    assets:cash  $-1
+    income:cash
+
+2024-01-20 Shop
+    assets:cash  $-1
+    expenses:food
+",
+        );
+        balance::resolve(&mut journal, false).unwrap();
+        assert!(run(&journal, &[Check::Commodities]).is_empty());
+
+        let diagnostics = run(&journal, &Check::ALL);
+        let messages = diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.check != Check::Assertions)
+            .map(|diagnostic| (diagnostic.check, diagnostic.message.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            vec![
+                (Check::Accounts, "account expenses:food is not declared"),
+                (Check::Accounts, "account income:cash is not declared"),
+                (Check::Payees, "payee Other is not declared"),
+                (Check::Tags, "tag type is not declared"),
+                (
+                    Check::OrderedDates,
+                    "transaction date 2024-01-01 is before the previous transaction date 2024-01-05"
+                ),
+                (
+                    Check::UniqueLeafNames,
+                    "account leaf name cash is not unique, it is used by assets:cash, income:cash"
+                ),
+                (
+                    Check::RecentAssertions,
+                    "the latest balance assertion of assets:cash is 15 days before its latest posting"
+                ),
+            ]
+        );
+        let assertion = diagnostics
+            .iter()
+            .find(|diagnostic| diagnostic.check == Check::Assertions)
+            .unwrap();
+        assert_eq!(assertion.position.start, 57);
+        assert_eq!(assertion.posting, Some(88..114));
+        assert_eq!(
+            assertion.message,
+            "balance assertion of assets:cash expected -1 $ but is -2 $"
+        );
+        let postings = diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.check == Check::Accounts)
+            .map(|diagnostic| diagnostic.posting.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(postings, vec![Some(115..155), Some(195..210)]);
+        let payee = diagnostics
+            .iter()
+            .find(|diagnostic| diagnostic.check == Check::Payees)
+            .unwrap();
+        assert_eq!(payee.posting, None);
+    }
+}
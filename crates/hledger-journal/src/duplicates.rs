@@ -0,0 +1,169 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use rust_decimal::Decimal;
+
+use crate::{Journal, Transaction};
+
+/// How alike transactions must be to be reported as duplicates. Their amounts per account must
+/// always be the same.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tolerance {
+    /// Maximum number of days between their dates.
+    pub days: u64,
+    /// Minimum similarity of their payees, from 0 for any payees to 1 for payees that only
+    /// differ in case, punctuation and spacing.
+    pub payee_similarity: f64,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Self {
+            days: 3,
+            payee_similarity: 0.5,
+        }
+    }
+}
+
+impl Tolerance {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn with_days(mut self, days: u64) -> Self {
+        self.days = days;
+        self
+    }
+
+    #[must_use]
+    pub fn with_payee_similarity(mut self, payee_similarity: f64) -> Self {
+        self.payee_similarity = payee_similarity;
+        self
+    }
+}
+
+// amounts per account and commodity, without postings added by auto posting rules
+fn signature(transaction: &Transaction) -> Vec<(String, String, Decimal)> {
+    let mut amounts = BTreeMap::<(String, String), Decimal>::new();
+    for posting in transaction.postings.iter().filter(|p| !p.is_generated) {
+        for amount in &posting.amount {
+            *amounts
+                .entry((posting.account_name.to_string(), amount.commodity.clone()))
+                .or_default() += amount.quantity;
+        }
+    }
+    amounts
+        .into_iter()
+        .filter(|(_, quantity)| !quantity.is_zero())
+        .map(|((account, commodity), quantity)| (account, commodity, quantity.normalize()))
+        .collect()
+}
+
+// Dice coefficient of the character pairs of the payees, ignoring case and anything that is not
+// a letter or a digit
+fn similarity(a: &str, b: &str) -> f64 {
+    let normalize = |payee: &str| {
+        payee
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(char::to_lowercase)
+            .collect::<Vec<_>>()
+    };
+    let (a, b) = (normalize(a), normalize(b));
+    if a == b {
+        return 1.0;
+    }
+    let pairs = |chars: &[char]| {
+        let mut pairs = HashMap::<(char, char), usize>::new();
+        for pair in chars.windows(2) {
+            *pairs.entry((pair[0], pair[1])).or_default() += 1;
+        }
+        pairs
+    };
+    let (a_pairs, b_pairs) = (pairs(&a), pairs(&b));
+    let total = a.len().saturating_sub(1) + b.len().saturating_sub(1);
+    if total == 0 {
+        return 0.0;
+    }
+    let common = a_pairs
+        .iter()
+        .map(|(pair, count)| (*count).min(b_pairs.get(pair).copied().unwrap_or_default()))
+        .sum::<usize>();
+    #[allow(clippy::cast_precision_loss)]
+    let similarity = (2 * common) as f64 / total as f64;
+    similarity
+}
+
+/// Groups transactions of the journal and its includes that are likely duplicates of each other.
+///
+/// Two transactions are alike when they have the same amounts per account, their dates are close
+/// and their payees similar. Groups are made of transactions alike, directly or through other
+/// transactions of the group, and are sorted by date, like their transactions.
+pub(crate) fn duplicates<'a>(
+    journal: &'a Journal,
+    tolerance: &Tolerance,
+) -> Vec<Vec<(&'a Path, &'a Transaction)>> {
+    let transactions = journal
+        .transactions_by_date()
+        .filter(|(_, transaction)| !transaction.is_generated)
+        .collect::<Vec<_>>();
+
+    let mut candidates = HashMap::<_, Vec<usize>>::new();
+    for (index, (_, transaction)) in transactions.iter().enumerate() {
+        let signature = signature(transaction);
+        if !signature.is_empty() {
+            candidates.entry(signature).or_default().push(index);
+        }
+    }
+
+    // group of every transaction, as the index of the earliest transaction of the group
+    let mut groups = (0..transactions.len()).collect::<Vec<_>>();
+    let find = |groups: &[usize], mut index: usize| {
+        while groups[index] != index {
+            index = groups[index];
+        }
+        index
+    };
+    for indices in candidates.values() {
+        for (i, &a) in indices.iter().enumerate() {
+            for &b in &indices[i + 1..] {
+                let (a_tx, b_tx) = (transactions[a].1, transactions[b].1);
+                // indices are sorted by date, later ones are even further apart
+                if (b_tx.date - a_tx.date).num_days().unsigned_abs() > tolerance.days {
+                    break;
+                }
+                if similarity(&a_tx.payee, &b_tx.payee) >= tolerance.payee_similarity {
+                    let (a_group, b_group) = (find(&groups, a), find(&groups, b));
+                    groups[a_group.max(b_group)] = a_group.min(b_group);
+                }
+            }
+        }
+    }
+
+    let mut duplicates = BTreeMap::<usize, Vec<_>>::new();
+    for (index, transaction) in transactions.iter().enumerate() {
+        duplicates
+            .entry(find(&groups, index))
+            .or_default()
+            .push(*transaction);
+    }
+    duplicates
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn payee_similarity() {
+        assert!((similarity("ACME Corp.", "acme corp") - 1.0).abs() < f64::EPSILON);
+        assert!(similarity("Whole Foods", "WHOLEFOODS MARKET #123") > 0.5);
+        assert!(similarity("Whole Foods", "Gas station") < 0.2);
+        assert!(similarity("", "Gas station").abs() < f64::EPSILON);
+    }
+}
@@ -0,0 +1,131 @@
+use hledger_parser::{occurrences, Period};
+
+use crate::{balance, include_chains, Error, Journal, Transaction};
+
+// like hledger, forecasts without an end cover 180 days from today
+const DEFAULT_DAYS: u64 = 180;
+
+/// Expands periodic transactions into transactions on every date they occur within the window,
+/// sorted by date.
+///
+/// A window without a begin date starts the day after the latest transaction, or today when
+/// there are none. A window without an end date ends 180 days after today.
+pub(crate) fn forecast(
+    journal: &Journal,
+    window: &Period,
+    today: chrono::NaiveDate,
+) -> Result<Vec<Transaction>, Error> {
+    let begin = window.begin.unwrap_or_else(|| {
+        journal
+            .transactions()
+            .map(|(_, transaction)| transaction.date)
+            .max()
+            .and_then(|date| date.succ_opt())
+            .unwrap_or(today)
+    });
+    let end = window.end.unwrap_or_else(|| {
+        today
+            .checked_add_days(chrono::Days::new(DEFAULT_DAYS))
+            .unwrap_or(today)
+    });
+    let window = Period {
+        begin: Some(begin),
+        end: Some(end),
+    };
+
+    let chains = include_chains(journal);
+    let mut transactions = Vec::new();
+    for (path, periodic) in journal.periodic_transactions() {
+        let Some(interval) = periodic.interval.as_ref() else {
+            continue;
+        };
+        for date in occurrences(interval, periodic.period.as_ref(), &window) {
+            let mut transaction = Transaction {
+                date,
+                status: periodic.status.clone(),
+                code: periodic.code.clone(),
+                payee: periodic.payee.clone(),
+                note: periodic.note.clone(),
+                postings: periodic.postings.clone(),
+                position: periodic.position.clone(),
+                is_generated: true,
+                comment: None,
+                tags: Vec::new(),
+            };
+            balance::balance(&mut transaction)
+                .map_err(|error| Error::in_file(&chains, path, error))?;
+            transactions.push(transaction);
+        }
+    }
+    transactions.sort_by_key(|transaction| transaction.date);
+    Ok(transactions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Amount;
+
+    #[test]
+    fn forecast_window() {
+        let journal = Journal::from_contents(
+            "~ monthly  rent
+    expenses:rent  $1000
+    assets:cash
+
+~ monthly from 2024-03-15 to 2024-05-01  salary
+    assets:cash  $2000
+    income
+
+2024-02-10 groceries
+    expenses:food  $50
+    assets:cash",
+        );
+        let date = |month, day| chrono::NaiveDate::from_ymd_opt(2024, month, day).unwrap();
+
+        let transactions = forecast(
+            &journal,
+            &Period {
+                begin: None,
+                end: Some(date(6, 1)),
+            },
+            date(2, 20),
+        )
+        .unwrap();
+        assert_eq!(
+            transactions
+                .iter()
+                .map(|transaction| (transaction.date, transaction.payee.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                (date(3, 1), "rent"),
+                (date(3, 15), "salary"),
+                (date(4, 1), "rent"),
+                (date(4, 15), "salary"),
+                (date(5, 1), "rent"),
+            ]
+        );
+        assert!(transactions
+            .iter()
+            .all(|transaction| transaction.is_generated));
+        assert_eq!(
+            transactions[0].postings[1].amount,
+            vec![Amount {
+                quantity: rust_decimal::Decimal::new(-1000, 0),
+                commodity: String::from("$"),
+                ..Amount::default()
+            }]
+        );
+
+        let transactions = forecast(
+            &journal,
+            &Period {
+                begin: None,
+                end: None,
+            },
+            date(2, 20),
+        )
+        .unwrap();
+        assert_eq!(transactions.last().map(|t| t.date), Some(date(8, 1)));
+    }
+}
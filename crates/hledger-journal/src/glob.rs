@@ -1,6 +1,75 @@
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use futures::StreamExt;
+use wax::Pattern;
+
+use crate::Error;
+
+/// Resolves an include pattern to the files it matches, sorted by path.
+///
+/// Relative patterns are resolved against `directory` and `~` stands for the home directory.
+/// Only the literal prefix of the pattern is walked, and only as deep as the pattern can match. A
+/// pattern without wildcards resolves to its path, whether the file exists or not, so that a
+/// missing include is reported when loading it.
+pub async fn resolve(directory: &Path, pattern: &str) -> Result<Vec<PathBuf>, Error> {
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    let pattern = expand_home(pattern, home.as_deref());
+    let glob = wax::Glob::new(&pattern).map_err(|error| Error::Glob(Arc::new(error)))?;
+    if let wax::Variance::Invariant(path) = glob.variance() {
+        return Ok(vec![directory.join(path)]);
+    }
+
+    let (prefix, glob) = glob.partition();
+    let start = directory.join(prefix);
+    let mut walker = walk(&start, &glob);
+    if let Some(depth) = max_depth(&glob.to_string()) {
+        walker = walker.with_max_depth(depth);
+    }
+    let entries = walker.as_stream().collect::<Vec<_>>().await;
+    let mut paths = Vec::new();
+    for entry in entries {
+        let path = entry.map_err(|error| {
+            // the walker keeps its io error, copy it with the path in the message
+            let kind = error
+                .io()
+                .map_or(std::io::ErrorKind::Other, std::io::Error::kind);
+            Error::Io(Arc::new(std::io::Error::new(kind, error.to_string())))
+        })?;
+        // directories are matched too, only files can be included
+        if async_fs::metadata(&path)
+            .await
+            .is_ok_and(|metadata| metadata.is_file())
+        {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+// Number of path components the pattern can match at most, or `None` when it can match any
+// number. Every separator may start a component, even within alternatives, so this is an upper
+// bound. Tree wildcards and repetitions match any number of components.
+fn max_depth(pattern: &str) -> Option<usize> {
+    if pattern.contains("**") || pattern.contains('<') {
+        None
+    } else {
+        Some(pattern.matches('/').count() + 1)
+    }
+}
+
+// replaces a leading `~` with the home directory, escaped so it matches literally
+fn expand_home(pattern: &str, home: Option<&Path>) -> String {
+    let rest = match pattern.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => rest,
+        _ => return pattern.to_string(),
+    };
+    match home.and_then(Path::to_str) {
+        Some(home) => format!("{}{rest}", wax::escape(home)),
+        None => pattern.to_string(),
+    }
+}
 
 pub fn walk<'glob, P: wax::Pattern<'glob>>(path: &Path, glob: &'glob P) -> Walker<'glob, P> {
     Walker::new(path, glob)
@@ -21,6 +90,30 @@ impl<'glob, P: wax::Pattern<'glob>> Walker<'glob, P> {
         }
     }
 
+    /// Does not walk into directories more than `depth` components below the start path.
+    #[must_use]
+    pub fn with_max_depth(mut self, depth: usize) -> Self {
+        let start_path = self.start_path.clone();
+        self.fs = async_walkdir::WalkDir::new(&self.start_path).filter(move |entry| {
+            let depth_of_entry = entry
+                .path()
+                .strip_prefix(&start_path)
+                .map_or(0, |path| path.components().count());
+            async move {
+                let is_dir = entry
+                    .file_type()
+                    .await
+                    .is_ok_and(|file_type| file_type.is_dir());
+                if is_dir && depth_of_entry >= depth {
+                    async_walkdir::Filtering::IgnoreDir
+                } else {
+                    async_walkdir::Filtering::Continue
+                }
+            }
+        });
+        self
+    }
+
     fn matches(&self, path: &Path) -> bool {
         self.pattern
             .is_match(path.strip_prefix(&self.start_path).unwrap())
@@ -32,7 +125,7 @@ impl<'glob, P: wax::Pattern<'glob>> Walker<'glob, P> {
                 Some(Ok(entry)) if self.matches(&entry.path()) => return Ok(Some(entry.path())),
                 Some(Err(error)) => return Err(error),
                 None => return Ok(None),
-                _ => continue,
+                _ => {}
             }
         }
     }
@@ -79,6 +172,81 @@ mod tests {
         assert_eq!(paths, expected);
     }
 
+    #[apply(smol_macros::test!)]
+    async fn resolve_sorted() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        for p in [
+            "journals/b.journal",
+            "journals/a.journal",
+            "journals/x/c.journal",
+            "d.journal",
+        ] {
+            let path = temp_dir.path().join(p);
+            async_fs::create_dir_all(path.parent().unwrap())
+                .await
+                .unwrap();
+            async_fs::write(path, b"").await.unwrap();
+        }
+
+        let paths = resolve(temp_dir.path(), "journals/**/*.journal")
+            .await
+            .unwrap();
+        let expected = [
+            "journals/a.journal",
+            "journals/b.journal",
+            "journals/x/c.journal",
+        ]
+        .into_iter()
+        .map(|p| temp_dir.path().join(p))
+        .collect::<Vec<_>>();
+        assert_eq!(paths, expected);
+
+        let absolute = format!("{}/*.journal", temp_dir.path().display());
+        let paths = resolve(Path::new("/nonexistent"), &absolute).await.unwrap();
+        assert_eq!(paths, vec![temp_dir.path().join("d.journal")]);
+
+        let paths = resolve(temp_dir.path(), "missing.journal").await.unwrap();
+        assert_eq!(paths, vec![temp_dir.path().join("missing.journal")]);
+    }
+
+    #[apply(smol_macros::test!)]
+    async fn resolve_depth() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        for p in ["a.journal", "x/b.journal", "x/y/c.journal"] {
+            let path = temp_dir.path().join(p);
+            async_fs::create_dir_all(path.parent().unwrap())
+                .await
+                .unwrap();
+            async_fs::write(path, b"").await.unwrap();
+        }
+
+        // deeper directories are not walked into
+        let glob = wax::Glob::new("*.journal").unwrap();
+        let mut walker = walk(temp_dir.path(), &glob).with_max_depth(1);
+        let mut visited = vec![];
+        while let Some(path) = walker.fs.next().await {
+            visited.push(path.unwrap().path());
+        }
+        assert_eq!(visited, vec![temp_dir.path().join("a.journal")]);
+
+        let paths = resolve(temp_dir.path(), "*/*.journal").await.unwrap();
+        assert_eq!(paths, vec![temp_dir.path().join("x/b.journal")]);
+
+        assert_eq!(max_depth("*.journal"), Some(1));
+        assert_eq!(max_depth("{a/*,b}.journal"), Some(2));
+        assert_eq!(max_depth("a/**/*.journal"), None);
+    }
+
+    #[test]
+    fn home_expansion() {
+        let home = Some(Path::new("/home/user"));
+        assert_eq!(expand_home("~/a.journal", home), "/home/user/a.journal");
+        assert_eq!(expand_home("~", home), "/home/user");
+        assert_eq!(expand_home("~user/a.journal", home), "~user/a.journal");
+        assert_eq!(expand_home("a.journal", home), "a.journal");
+        assert_eq!(expand_home("~/a.journal", None), "~/a.journal");
+    }
+
     #[apply(smol_macros::test!)]
     async fn glob_walker_basic() {
         glob_walker_test("*.txt", vec!["a.txt", "b.bin"], vec!["a.txt"]).await;
@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::Path;
+
+use hledger_parser::Directive;
+
+/// Identifier of a transaction that stays the same when the journal is loaded again, as long as
+/// the text of the transaction and the path of its file do not change.
+///
+/// It is made of hashes of the path and of the text, and of the occurrence of the text among
+/// the transactions of the file with the same text. Hashes may differ between builds of the
+/// program, so identifiers are not meant to be stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TransactionId {
+    file: u64,
+    content: u64,
+    occurrence: usize,
+}
+
+fn hash<T: Hash + ?Sized>(value: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Identifiers of the transactions of a file, in parse order.
+pub(crate) fn ids(path: &Path, contents: &str, directives: &[Directive]) -> Vec<TransactionId> {
+    let file = hash(path);
+    let mut occurrences = HashMap::<u64, usize>::new();
+    directives
+        .iter()
+        .filter_map(|directive| match directive {
+            Directive::Transaction(transaction) => Some(transaction),
+            _ => None,
+        })
+        .map(|transaction| {
+            let content = hash(
+                contents
+                    .get(transaction.position.clone())
+                    .unwrap_or_default(),
+            );
+            let occurrence = occurrences.entry(content).or_default();
+            *occurrence += 1;
+            TransactionId {
+                file,
+                content,
+                occurrence: *occurrence - 1,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids_of(contents: &str) -> Vec<TransactionId> {
+        let directives = hledger_parser::parse(contents).unwrap();
+        ids(Path::new("main.journal"), contents, &directives)
+    }
+
+    #[test]
+    fn stable_after_edits_above() {
+        let before = ids_of("2024-01-01 a\n    a  $1\n    b\n\n2024-01-02 b\n    a  $1\n    b\n");
+        let after = ids_of(
+            "2023-12-31 new\n    a  $1\n    b\n\n2024-01-01 a\n    a  $1\n    b\n\n2024-01-02 b\n    a  $1\n    b\n",
+        );
+        assert_eq!(before, after[1..]);
+    }
+
+    #[test]
+    fn identical_transactions() {
+        let ids = ids_of("2024-01-01 a\n    a  $1\n    b\n\n2024-01-01 a\n    a  $1\n    b\n");
+        assert_ne!(ids[0], ids[1]);
+        assert_eq!(ids[0].content, ids[1].content);
+        assert_eq!((ids[0].occurrence, ids[1].occurrence), (0, 1));
+    }
+}
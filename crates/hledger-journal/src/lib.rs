@@ -1,139 +1,1195 @@
+mod auto;
+mod balance;
+mod checks;
+mod duplicates;
+mod forecast;
 mod glob;
+mod id;
+mod options;
+mod prices;
+mod tree;
+mod writer;
 
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 use futures::{
     channel::oneshot,
     stream::{self, StreamExt},
 };
-use hledger_parser::{Directive, Format, Include};
-
-use crate::glob::walk;
+use hledger_parser::{
+    Account, ApplyAccount, AutosPostingRule, Commodity, DecimalMark, Directive, Format, Include,
+    Payee, PeriodicTransaction, Price, Tag, Unsupported, Year,
+};
 
 #[derive(Debug, Clone)]
 pub struct Journal {
     pub path: std::path::PathBuf,
-    transactions: Vec<hledger_parser::Transaction>,
-    includes: Vec<Journal>,
+    directives: Vec<Directive>,
+    // text the directives were parsed from, to detect changes before writing
+    contents: Arc<str>,
+    // identifiers of the transactions of the file, in parse order
+    ids: Vec<TransactionId>,
+    // journals included by the include directive at the index, sorted by index and path
+    includes: Vec<(usize, Journal)>,
 }
 
-pub use hledger_parser::{Amount, ParseError, Posting, Transaction};
+pub use hledger_parser::{Amount, ParseError, ParseOptions, Period, Posting, Transaction};
+
+pub use crate::balance::AssertionFailure;
+pub use crate::checks::{Check, Diagnostic};
+pub use crate::duplicates::Tolerance;
+pub use crate::id::TransactionId;
+pub use crate::options::LoadOptions;
+pub use crate::prices::{Prices, Valuation};
+pub use crate::tree::AccountTree;
 
 #[derive(Debug, Clone, thiserror::Error)]
 pub enum Error {
     #[error("io: {0}")]
-    Io(std::io::ErrorKind),
+    Io(Arc<std::io::Error>),
     #[error("failed to parse glob")]
     Glob(Arc<wax::BuildError>),
     #[error("failed to parse file")]
-    Parse(Vec<ParseError>),
+    Parse {
+        /// Contents of the file, the spans of the errors point into it.
+        contents: Arc<str>,
+        errors: Vec<ParseError>,
+    },
+    #[error("failed to parse query")]
+    Query(Arc<hledger_query::Error>),
+    #[error("{}: {error}", path.display())]
+    File {
+        path: std::path::PathBuf,
+        /// Files from the root journal to the one including `path`.
+        includes: Vec<std::path::PathBuf>,
+        error: Box<Error>,
+    },
+    #[error("{} includes itself", path.display())]
+    IncludeCycle {
+        path: std::path::PathBuf,
+        /// Files from the root journal to the one including `path` again.
+        includes: Vec<std::path::PathBuf>,
+    },
+    #[error("{} is included more than once", path.display())]
+    DuplicateInclude {
+        path: std::path::PathBuf,
+        /// Files from the root journal to the one including `path` the second time.
+        includes: Vec<std::path::PathBuf>,
+    },
+    #[error("{} errors in the journal and its includes", .0.len())]
+    Multiple(Vec<Error>),
+    // errors below are wrapped in `Error::File`, with the file they are about
+    #[error("{} is not part of the journal", .0.display())]
+    NotInJournal(std::path::PathBuf),
+    #[error("file changed since it was loaded")]
+    FileChanged,
+    #[error("there is no transaction at {}", position.start)]
+    TransactionNotFound { position: std::ops::Range<usize> },
+    #[error("account {account} of the transaction at {} is not declared", position.start)]
+    UndeclaredAccount {
+        account: String,
+        /// Position of the transaction using the account.
+        position: std::ops::Range<usize>,
+    },
+    #[error("commodity {commodity} of the transaction at {} is not declared", position.start)]
+    UndeclaredCommodity {
+        commodity: String,
+        /// Position of the transaction using the commodity.
+        position: std::ops::Range<usize>,
+    },
+    #[error("transaction at {} does not balance", position.start)]
+    UnbalancedTransaction {
+        position: std::ops::Range<usize>,
+        /// Sum of the postings that should be zero, per commodity.
+        amounts: Vec<Amount>,
+    },
+    #[error(
+        "transaction at {} has more than one posting without an amount",
+        position.start
+    )]
+    TooManyMissingAmounts { position: std::ops::Range<usize> },
+    #[error("amounts of the transaction at {} are too large to add up", position.start)]
+    Overflow { position: std::ops::Range<usize> },
+    #[error(
+        "transaction at {} assigns the balance of {account}, which is matched by auto postings",
+        position.start
+    )]
+    AutoPostingsOnAssignment {
+        position: std::ops::Range<usize>,
+        account: String,
+    },
+    #[error(
+        "auto posting rule at {}:{} can not compute the amounts for the transaction at {}",
+        rule.0.display(),
+        rule.1.start,
+        position.start
+    )]
+    AutoPostingAmount {
+        /// Position of the transaction with the matched posting.
+        position: std::ops::Range<usize>,
+        /// File and position of the rule.
+        rule: (std::path::PathBuf, std::ops::Range<usize>),
+    },
+}
+
+impl Error {
+    /// Every error, with [`Error::Multiple`] flattened.
+    pub fn errors(&self) -> impl Iterator<Item = &Error> {
+        let errors: Box<dyn Iterator<Item = &Error>> = match self {
+            Error::Multiple(errors) => Box::new(errors.iter().flat_map(Error::errors)),
+            error => Box::new(std::iter::once(error)),
+        };
+        errors
+    }
+
+    // wraps the error about a file of the journal, with the files including it
+    pub(crate) fn in_file(chains: &IncludeChains, path: &std::path::Path, error: Error) -> Error {
+        Error::File {
+            path: path.to_path_buf(),
+            includes: chains.get(path).cloned().unwrap_or_default(),
+            error: Box::new(error),
+        }
+    }
+
+    // a single error stays as it is
+    pub(crate) fn from_errors(mut errors: Vec<Error>) -> Option<Error> {
+        match errors.len() {
+            0 => None,
+            1 => errors.pop(),
+            _ => Some(Error::Multiple(errors)),
+        }
+    }
 }
 
 impl Journal {
     #[allow(clippy::missing_errors_doc)]
     pub async fn load<P: AsRef<std::path::Path>>(path: P) -> Result<Self, Error> {
-        load(path).await
+        Self::load_with_options(path, &LoadOptions::default()).await
+    }
+
+    /// Loads the journal, parsing it and every included file with the parse options.
+    ///
+    /// Errors of a file are wrapped in [`Error::File`] with the chain of files including it. A
+    /// file including itself, directly or not, or included more than once is an error. Loading
+    /// goes on after a file fails, errors of all files are returned in [`Error::Multiple`].
+    #[allow(clippy::missing_errors_doc)]
+    pub async fn load_with_options<P: AsRef<std::path::Path>>(
+        path: P,
+        options: &LoadOptions,
+    ) -> Result<Self, Error> {
+        let mut journal = load(path, &options.parse, &[]).await?;
+        check_duplicate_includes(&journal).await?;
+        balance::resolve(&mut journal, options.lenient)?;
+        if options.auto {
+            auto::apply(&mut journal, options.lenient)?;
+        }
+        if options.strict {
+            check_declarations(&journal)?;
+        }
+        Ok(journal)
     }
 
+    /// Paths of the journal and every file it includes, directly or not.
     pub fn includes(&self) -> impl Iterator<Item = std::path::PathBuf> + '_ {
-        std::iter::once(self.path.clone())
-            .chain(self.includes.iter().map(|journal| journal.path.clone()))
+        journals(self).map(|journal| journal.path.clone())
     }
 
-    pub fn transactions(&self) -> impl Iterator<Item = &Transaction> {
-        self.transactions.iter().chain(
-            self.includes
-                .iter()
-                .flat_map(|included| included.transactions.iter()),
-        )
+    /// Checks balance assertions of the journal and its includes, returning every assertion
+    /// that does not hold.
+    #[allow(clippy::missing_errors_doc)]
+    pub fn check_assertions(&self) -> Result<(), Vec<AssertionFailure>> {
+        let failures = balance::check_assertions(self);
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(failures)
+        }
     }
 
-    pub fn merge(&mut self, other: &Journal) -> bool {
-        if self.path == other.path {
-            self.transactions.clone_from(&other.transactions);
-            self.includes.clone_from(&other.includes);
-            true
+    /// Runs the checks, like `hledger check`, returning every problem found.
+    #[allow(clippy::missing_errors_doc)]
+    pub fn check(&self, checks: &[Check]) -> Result<(), Vec<Diagnostic>> {
+        let diagnostics = checks::run(self, checks);
+        if diagnostics.is_empty() {
+            Ok(())
         } else {
-            for included in &mut self.includes {
-                if included.merge(other) {
-                    return true;
-                }
+            Err(diagnostics)
+        }
+    }
+
+    /// Groups of transactions of the journal and its includes that are likely duplicates, like
+    /// the same bank transaction imported twice, with the file they came from.
+    #[must_use]
+    pub fn duplicates(&self, tolerance: &Tolerance) -> Vec<Vec<(&std::path::Path, &Transaction)>> {
+        duplicates::duplicates(self, tolerance)
+    }
+
+    /// Appends the transaction to a file of the journal, after an empty line.
+    ///
+    /// Files are replaced atomically, and only when they did not change since they were loaded.
+    /// The journal itself is not updated, it has to be loaded again to see the change.
+    #[allow(clippy::missing_errors_doc)]
+    pub async fn append_transaction(
+        &self,
+        path: &std::path::Path,
+        transaction: &Transaction,
+    ) -> Result<(), Error> {
+        writer::append(self, path, transaction).await
+    }
+
+    /// Replaces the transaction at the position in a file of the journal, like
+    /// [`Journal::append_transaction`].
+    #[allow(clippy::missing_errors_doc)]
+    pub async fn replace_transaction(
+        &self,
+        path: &std::path::Path,
+        position: std::ops::Range<usize>,
+        transaction: &Transaction,
+    ) -> Result<(), Error> {
+        writer::replace(self, path, position, transaction).await
+    }
+
+    /// Deletes the transaction at the position in a file of the journal, like
+    /// [`Journal::append_transaction`].
+    #[allow(clippy::missing_errors_doc)]
+    pub async fn delete_transaction(
+        &self,
+        path: &std::path::Path,
+        position: std::ops::Range<usize>,
+    ) -> Result<(), Error> {
+        writer::delete(self, path, position).await
+    }
+
+    /// Transactions generated by periodic transactions within the window, like
+    /// `hledger --forecast`, sorted by date and marked as generated.
+    ///
+    /// A window without a begin date starts the day after the latest transaction, or `today`
+    /// when there are none. A window without an end date ends 180 days after `today`.
+    #[allow(clippy::missing_errors_doc)]
+    pub fn forecast(
+        &self,
+        window: &Period,
+        today: chrono::NaiveDate,
+    ) -> Result<Vec<Transaction>, Error> {
+        forecast::forecast(self, window, today)
+    }
+
+    /// Market prices declared in the journal and its includes. With `implied`, prices of
+    /// postings are used too, like `hledger --infer-market-prices`.
+    #[must_use]
+    pub fn market_prices(&self, implied: bool) -> Prices {
+        Prices::from_journal(self, implied)
+    }
+
+    /// Accounts declared or posted to in the journal and its includes, with their balances.
+    #[must_use]
+    pub fn account_tree(&self) -> AccountTree {
+        AccountTree::from_journal(self)
+    }
+
+    /// Every directive of the journal and its includes, with the file it came from, in parse
+    /// order. Directives of included files follow their include directive.
+    pub fn directives(&self) -> impl Iterator<Item = (&std::path::Path, &Directive)> {
+        directives(self)
+    }
+
+    /// Transactions of the journal and its includes sorted by date, in parse order within a
+    /// date. This is the order balances are computed in.
+    pub fn transactions_by_date(&self) -> impl Iterator<Item = (&std::path::Path, &Transaction)> {
+        let mut transactions = self.transactions().collect::<Vec<_>>();
+        transactions.sort_by_key(|(_, transaction)| transaction.date);
+        transactions.into_iter()
+    }
+
+    /// Transactions of the journal and its includes with their identifier, in the order of
+    /// [`Journal::transactions`].
+    pub fn transactions_with_ids(
+        &self,
+    ) -> impl Iterator<Item = (TransactionId, &std::path::Path, &Transaction)> {
+        transactions_with_ids(self)
+    }
+
+    /// Returns the transaction with the identifier, with the file it came from.
+    #[must_use]
+    pub fn transaction(&self, id: TransactionId) -> Option<(&std::path::Path, &Transaction)> {
+        self.transactions_with_ids()
+            .find(|(transaction_id, _, _)| *transaction_id == id)
+            .map(|(_, path, transaction)| (path, transaction))
+    }
+
+    pub fn transactions(&self) -> impl Iterator<Item = (&std::path::Path, &Transaction)> {
+        self.directives()
+            .filter_map(|(path, directive)| match directive {
+                Directive::Transaction(transaction) => Some((path, transaction)),
+                _ => None,
+            })
+    }
+
+    pub fn periodic_transactions(
+        &self,
+    ) -> impl Iterator<Item = (&std::path::Path, &PeriodicTransaction)> {
+        self.directives()
+            .filter_map(|(path, directive)| match directive {
+                Directive::PeriodicTransaction(periodic) => Some((path, periodic)),
+                _ => None,
+            })
+    }
+
+    pub fn auto_postings(&self) -> impl Iterator<Item = (&std::path::Path, &AutosPostingRule)> {
+        self.directives()
+            .filter_map(|(path, directive)| match directive {
+                Directive::AutoPostings(rule) => Some((path, rule)),
+                _ => None,
+            })
+    }
+
+    pub fn accounts(&self) -> impl Iterator<Item = (&std::path::Path, &Account)> {
+        self.directives()
+            .filter_map(|(path, directive)| match directive {
+                Directive::Account(account) => Some((path, account)),
+                _ => None,
+            })
+    }
+
+    pub fn commodities(&self) -> impl Iterator<Item = (&std::path::Path, &Commodity)> {
+        self.directives()
+            .filter_map(|(path, directive)| match directive {
+                Directive::Commodity(commodity) => Some((path, commodity)),
+                _ => None,
+            })
+    }
+
+    pub fn prices(&self) -> impl Iterator<Item = (&std::path::Path, &Price)> {
+        self.directives()
+            .filter_map(|(path, directive)| match directive {
+                Directive::Price(price) => Some((path, price)),
+                _ => None,
+            })
+    }
+
+    /// Payees declared with `payee` directives in the journal and its includes.
+    pub fn payees(&self) -> impl Iterator<Item = (&std::path::Path, &Payee)> {
+        self.directives()
+            .filter_map(|(path, directive)| match directive {
+                Directive::Payee(payee) => Some((path, payee)),
+                _ => None,
+            })
+    }
+
+    /// Tags declared with `tag` directives in the journal and its includes.
+    pub fn tags(&self) -> impl Iterator<Item = (&std::path::Path, &Tag)> {
+        self.directives()
+            .filter_map(|(path, directive)| match directive {
+                Directive::Tag(tag) => Some((path, tag)),
+                _ => None,
+            })
+    }
+
+    /// Directives that were parsed but are not supported, like ledger's `apply tag`.
+    pub fn unsupported(&self) -> impl Iterator<Item = (&std::path::Path, &Unsupported)> {
+        self.directives()
+            .filter_map(|(path, directive)| match directive {
+                Directive::Unsupported(unsupported) => Some((path, unsupported)),
+                _ => None,
+            })
+    }
+}
+
+// files from the root journal to the one including each file of the journal
+pub(crate) type IncludeChains = HashMap<std::path::PathBuf, Vec<std::path::PathBuf>>;
+
+pub(crate) fn include_chains(journal: &Journal) -> IncludeChains {
+    let mut chains = HashMap::new();
+    let mut stack = vec![(journal, Vec::new())];
+    while let Some((journal, includes)) = stack.pop() {
+        let mut chain = includes.clone();
+        chain.push(journal.path.clone());
+        stack.extend(
+            journal
+                .includes
+                .iter()
+                .map(|(_, included)| (included, chain.clone())),
+        );
+        chains.insert(journal.path.clone(), includes);
+    }
+    chains
+}
+
+fn journals(journal: &Journal) -> Box<dyn Iterator<Item = &Journal> + '_> {
+    Box::new(
+        std::iter::once(journal).chain(
+            journal
+                .includes
+                .iter()
+                .flat_map(|(_, included)| journals(included)),
+        ),
+    )
+}
+
+fn directives(journal: &Journal) -> Box<dyn Iterator<Item = (&std::path::Path, &Directive)> + '_> {
+    let path = journal.path.as_path();
+    Box::new(
+        journal
+            .directives
+            .iter()
+            .enumerate()
+            .flat_map(move |(index, directive)| {
+                let included = journal
+                    .includes
+                    .iter()
+                    .filter(move |(at, _)| *at == index)
+                    .flat_map(|(_, included)| directives(included));
+                std::iter::once((path, directive)).chain(included)
+            }),
+    )
+}
+
+fn transactions_with_ids(
+    journal: &Journal,
+) -> Box<dyn Iterator<Item = (TransactionId, &std::path::Path, &Transaction)> + '_> {
+    let path = journal.path.as_path();
+    let mut ids = journal.ids.iter();
+    Box::new(
+        journal
+            .directives
+            .iter()
+            .enumerate()
+            .flat_map(move |(index, directive)| {
+                let transaction = match directive {
+                    Directive::Transaction(transaction) => {
+                        ids.next().map(|id| (*id, path, transaction))
+                    }
+                    _ => None,
+                };
+                let included = journal
+                    .includes
+                    .iter()
+                    .filter(move |(at, _)| *at == index)
+                    .flat_map(|(_, included)| transactions_with_ids(included));
+                transaction.into_iter().chain(included)
+            }),
+    )
+}
+
+// in strict mode, every account and commodity used in postings must be declared somewhere in the
+// journal or its includes.
+fn check_declarations(journal: &Journal) -> Result<(), Error> {
+    let chains = include_chains(journal);
+    let accounts = journal
+        .accounts()
+        .map(|(_, account)| account.account_name.to_string())
+        .collect::<HashSet<_>>();
+    let commodities = journal
+        .commodities()
+        .map(|(_, commodity)| match commodity {
+            Commodity::Amount(amount) => amount.commodity.clone(),
+            Commodity::Commodity(commodity) => commodity.clone(),
+        })
+        .collect::<HashSet<_>>();
+    for (path, transaction, posting) in journal
+        .transactions()
+        .flat_map(|(path, tx)| tx.postings.iter().map(move |posting| (path, tx, posting)))
+    {
+        let account_name = posting.account_name.to_string();
+        if !accounts.contains(&account_name) {
+            let error = Error::UndeclaredAccount {
+                account: account_name,
+                position: transaction.position.clone(),
+            };
+            return Err(Error::in_file(&chains, path, error));
+        }
+        let costs = posting
+            .amount
+            .iter()
+            .filter_map(Amount::cost)
+            .collect::<Vec<_>>();
+        for amount in posting.amount.iter().chain(costs.iter()) {
+            if !amount.commodity.is_empty() && !commodities.contains(&amount.commodity) {
+                let error = Error::UndeclaredCommodity {
+                    commodity: amount.commodity.clone(),
+                    position: transaction.position.clone(),
+                };
+                return Err(Error::in_file(&chains, path, error));
             }
-            false
         }
     }
+    Ok(())
 }
 
-async fn parse<P: AsRef<std::path::Path>>(path: P) -> Result<Vec<Directive>, Error> {
-    let contents = async_fs::read_to_string(&path)
+async fn parse<P: AsRef<std::path::Path>>(
+    path: P,
+    options: &ParseOptions,
+) -> Result<(Arc<str>, Vec<Directive>), Error> {
+    let contents: Arc<str> = async_fs::read_to_string(&path)
         .await
-        .map_err(|error| Error::Io(error.kind()))?;
+        .map_err(|error| Error::Io(Arc::new(error)))?
+        .into();
+    // the path picks the format of the file
+    let options = options.clone().with_path(path);
     let (send, recv) = oneshot::channel();
     rayon::spawn(move || {
-        let result = hledger_parser::parse(contents).map_err(Error::Parse);
+        let result = match hledger_parser::parse_with_options(&contents, &options) {
+            Ok(directives) => Ok((contents, directives)),
+            Err(errors) => Err(Error::Parse { contents, errors }),
+        };
         let _ = send.send(result);
     });
     recv.await.expect("panic in rayon::spawn")
 }
 
 #[tracing::instrument(skip_all, fields(path = %path.as_ref().display()))]
-async fn load<P: AsRef<std::path::Path>>(path: P) -> Result<Journal, Error> {
+async fn load<P: AsRef<std::path::Path>>(
+    path: P,
+    options: &ParseOptions,
+    includes: &[(std::path::PathBuf, std::path::PathBuf)],
+) -> Result<Journal, Error> {
     let path = path.as_ref();
+    let in_file = |error| match error {
+        // errors of included files already know where they come from
+        Error::File { .. }
+        | Error::IncludeCycle { .. }
+        | Error::DuplicateInclude { .. }
+        | Error::Multiple(_) => error,
+        error => Error::File {
+            path: path.to_path_buf(),
+            includes: includes.iter().map(|(path, _)| path.clone()).collect(),
+            error: Box::new(error),
+        },
+    };
+
+    let canonical = async_fs::canonicalize(path)
+        .await
+        .map_err(|error| in_file(Error::Io(Arc::new(error))))?;
+    if includes.iter().any(|(_, included)| *included == canonical) {
+        return Err(Error::IncludeCycle {
+            path: path.to_path_buf(),
+            includes: includes.iter().map(|(path, _)| path.clone()).collect(),
+        });
+    }
+    let mut chain = includes.to_vec();
+    chain.push((path.to_path_buf(), canonical));
 
-    let directives = parse(path).await?;
+    let (contents, directives) = parse(path, options).await.map_err(in_file)?;
 
-    let includes = directives
-        .iter()
-        .filter_map(|directive| match directive {
+    // included files are parsed with the state of this file at the include directive
+    let mut scope = options.clone();
+    let mut included = Vec::new();
+    let mut errors = Vec::new();
+    for (index, directive) in directives.iter().enumerate() {
+        match directive {
+            Directive::Year(Year(year)) => scope.year = *year,
+            Directive::DecimalMark(DecimalMark(mark)) => scope.decimal_mark = Some(*mark),
+            Directive::Alias(alias) => scope.aliases.push(alias.clone()),
+            Directive::EndAliases => scope.aliases.clear(),
+            Directive::ApplyAccount(ApplyAccount(account)) => {
+                scope.apply_accounts.push(account.clone());
+            }
+            Directive::EndApplyAccount => {
+                scope.apply_accounts.pop();
+            }
             Directive::Include(Include {
                 path: include_path,
                 format: None | Some(Format::Journal),
-            }) => Some(wax::Glob::new(include_path.as_os_str().to_str().unwrap())),
-            _ => None,
-        })
-        .collect::<Result<Vec<_>, _>>()
-        .map_err(|error| Error::Glob(Arc::new(error)))?;
-
-    let includes = load_many_globs(path.parent().unwrap(), includes).await?;
-    let transactions = directives
-        .into_iter()
-        .filter_map(|directive| match directive {
-            Directive::Transaction(tx) => Some(tx),
-            _ => None,
-        })
-        .collect::<Vec<_>>();
-
+            }) => {
+                let (journals, include_errors) = load_included(
+                    path.parent().unwrap(),
+                    &include_path.to_string_lossy(),
+                    &scope,
+                    &chain,
+                )
+                .await;
+                included.extend(journals.into_iter().map(|journal| (index, journal)));
+                errors.extend(include_errors.into_iter().map(in_file));
+            }
+            _ => {}
+        }
+    }
+    if let Some(error) = Error::from_errors(errors) {
+        return Err(error);
+    }
     Ok(Journal {
         path: path.to_path_buf(),
-        transactions,
-        includes,
+        ids: id::ids(path, &contents, &directives),
+        directives,
+        contents,
+        includes: included,
     })
 }
 
-async fn load_many_globs<'a, P: wax::Combine<'a>>(
-    path: &std::path::Path,
-    patterns: Vec<P>,
-) -> Result<Vec<Journal>, Error> {
-    let patterns = wax::any(patterns).map_err(|error| Error::Glob(Arc::new(error)))?;
-    let paths = walk(path, &patterns).as_stream().collect::<Vec<_>>().await;
-    let paths = paths
-        .into_iter()
-        .collect::<Result<Vec<_>, _>>()
-        .map_err(|error| {
-            if let Some(io_error) = error.io() {
-                Error::Io(io_error.kind())
-            } else {
-                Error::Io(std::io::ErrorKind::Other)
-            }
-        })?;
-    let journals = load_many(paths).await;
-    journals.into_iter().collect::<Result<Vec<_>, _>>()
+// loads every file matching the include pattern, returning the ones that loaded and the errors
+// of the others
+async fn load_included(
+    directory: &std::path::Path,
+    pattern: &str,
+    options: &ParseOptions,
+    includes: &[(std::path::PathBuf, std::path::PathBuf)],
+) -> (Vec<Journal>, Vec<Error>) {
+    let paths = match glob::resolve(directory, pattern).await {
+        Ok(paths) => paths,
+        Err(error) => return (Vec::new(), vec![error]),
+    };
+    let mut journals = Vec::new();
+    let mut errors = Vec::new();
+    for journal in load_many(paths, options, includes).await {
+        match journal {
+            Ok(journal) => journals.push(journal),
+            Err(Error::Multiple(multiple)) => errors.extend(multiple),
+            Err(error) => errors.push(error),
+        }
+    }
+    (journals, errors)
 }
 
-async fn load_many<P: AsRef<std::path::Path>>(paths: Vec<P>) -> Vec<Result<Journal, Error>> {
+async fn load_many<P: AsRef<std::path::Path>>(
+    paths: Vec<P>,
+    options: &ParseOptions,
+    includes: &[(std::path::PathBuf, std::path::PathBuf)],
+) -> Vec<Result<Journal, Error>> {
     stream::iter(paths)
-        .map(|path| Journal::load(path))
-        .buffer_unordered(1024)
+        .map(|path| load(path, options, includes))
+        .buffered(1024)
         .collect::<Vec<_>>()
         .await
 }
+
+// files may only be included once, the first inclusion in parse order is kept
+async fn check_duplicate_includes(journal: &Journal) -> Result<(), Error> {
+    let mut seen = HashSet::new();
+    let mut stack = vec![(journal, Vec::new())];
+    while let Some((journal, includes)) = stack.pop() {
+        let canonical = async_fs::canonicalize(&journal.path)
+            .await
+            .unwrap_or_else(|_| journal.path.clone());
+        if !seen.insert(canonical) {
+            return Err(Error::DuplicateInclude {
+                path: journal.path.clone(),
+                includes,
+            });
+        }
+        let mut chain = includes;
+        chain.push(journal.path.clone());
+        stack.extend(
+            journal
+                .includes
+                .iter()
+                .rev()
+                .map(|(_, included)| (included, chain.clone())),
+        );
+    }
+    Ok(())
+}
+
+// journal of a single file without includes, for tests of what is done with loaded journals
+#[cfg(test)]
+impl Journal {
+    pub(crate) fn from_contents(contents: &str) -> Journal {
+        let path = std::path::PathBuf::from("main.journal");
+        let directives = hledger_parser::parse(contents).unwrap();
+        Journal {
+            ids: id::ids(&path, contents, &directives),
+            path,
+            directives,
+            contents: contents.into(),
+            includes: Vec::new(),
+        }
+    }
+}
+
+// file of an error wrapped in `Error::File`, and the error
+#[cfg(test)]
+pub(crate) fn in_file(error: &Error) -> (&std::path::Path, &Error) {
+    match error {
+        Error::File { path, error, .. } => (path, error),
+        error => panic!("not an error of a file: {error}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use macro_rules_attribute::apply;
+
+    #[apply(smol_macros::test!)]
+    async fn strict_undeclared_account() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("main.journal");
+        async_fs::write(&path, "account assets\ninclude other.journal\n")
+            .await
+            .unwrap();
+        let other = temp_dir.path().join("other.journal");
+        async_fs::write(
+            &other,
+            "commodity $1.00\n2024-01-01 test\n    assets  $1\n    income",
+        )
+        .await
+        .unwrap();
+
+        let result =
+            Journal::load_with_options(&path, &LoadOptions::default().with_strict(true)).await;
+        assert!(matches!(
+            result,
+            Err(Error::File { path: file, includes, error })
+                if file == other
+                    && includes == [path.clone()]
+                    && matches!(*error, Error::UndeclaredAccount { ref account, .. } if account == "income")
+        ));
+
+        let result = Journal::load(&path).await;
+        assert!(result.is_ok());
+    }
+
+    #[apply(smol_macros::test!)]
+    async fn declared_payees_and_tags() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("main.journal");
+        async_fs::write(
+            &path,
+            "payee Whole Foods\n    ; category: groceries\ninclude other.journal",
+        )
+        .await
+        .unwrap();
+        async_fs::write(temp_dir.path().join("other.journal"), "tag trip\n")
+            .await
+            .unwrap();
+
+        let journal = Journal::load(&path).await.unwrap();
+        let payees = journal.payees().collect::<Vec<_>>();
+        assert_eq!(payees.len(), 1);
+        assert_eq!(payees[0].1.name, "Whole Foods");
+        assert_eq!(
+            payees[0].1.metadata.tags,
+            vec![(String::from("category"), String::from("groceries"))]
+        );
+        let tags = journal
+            .tags()
+            .map(|(_, tag)| tag.name.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(tags, vec![String::from("trip")]);
+    }
+
+    #[apply(smol_macros::test!)]
+    async fn typed_directives() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("main.journal");
+        async_fs::write(
+            &path,
+            "account assets\ncommodity $1.00\ninclude other.journal\n~ monthly  budget\n    assets  $1\n    income",
+        )
+        .await
+        .unwrap();
+        let other = temp_dir.path().join("other.journal");
+        async_fs::write(
+            &other,
+            "P 2024-01-01 EUR $1.10\n= assets\n    (budget)  *-1\n    (goal)  *1\n",
+        )
+        .await
+        .unwrap();
+
+        let journal = Journal::load(&path).await.unwrap();
+        assert_eq!(journal.accounts().count(), 1);
+        assert_eq!(journal.commodities().count(), 1);
+        assert_eq!(journal.periodic_transactions().count(), 1);
+        let prices = journal.prices().collect::<Vec<_>>();
+        assert_eq!(prices.len(), 1);
+        assert_eq!(prices[0].0, other.as_path());
+        assert_eq!(prices[0].1.commodity, "EUR");
+        let rules = journal.auto_postings().collect::<Vec<_>>();
+        assert_eq!(rules.len(), 1);
+        assert_eq!(rules[0].0, other.as_path());
+        assert_eq!(journal.directives().count(), 6);
+    }
+
+    #[apply(smol_macros::test!)]
+    async fn beancount() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("main.beancount");
+        async_fs::write(
+            &path,
+            "include \"other.beancount\"\n2024-01-01 * \"Deposit\"\n  Assets:Cash  1 USD\n  Income:Salary",
+        )
+        .await
+        .unwrap();
+        async_fs::write(
+            temp_dir.path().join("other.beancount"),
+            "2024-01-02 * \"Lunch\"\n  Expenses:Food  1 USD\n  Assets:Cash",
+        )
+        .await
+        .unwrap();
+
+        let journal = Journal::load(&path).await.unwrap();
+        let payees = journal
+            .transactions_by_date()
+            .map(|(_, tx)| tx.payee.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(payees, vec![String::from("Deposit"), String::from("Lunch")]);
+    }
+
+    #[apply(smol_macros::test!)]
+    async fn include_position_ordering() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("main.journal");
+        async_fs::write(
+            &path,
+            "2024-01-02 second\n  a  1\n  b\n\ninclude *.included\n\n2024-01-01 first\n  a  1\n  b\n\n2024-01-02 fifth\n  a  1\n  b\n",
+        )
+        .await
+        .unwrap();
+        async_fs::write(
+            temp_dir.path().join("b.included"),
+            "2024-01-02 fourth\n  a  1\n  b\n",
+        )
+        .await
+        .unwrap();
+        async_fs::write(
+            temp_dir.path().join("a.included"),
+            "2024-01-02 third\n  a  1\n  b\n",
+        )
+        .await
+        .unwrap();
+
+        let journal = Journal::load(&path).await.unwrap();
+        let payees = journal
+            .transactions_by_date()
+            .map(|(_, tx)| tx.payee.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(payees, vec!["first", "second", "third", "fourth", "fifth"]);
+    }
+
+    #[apply(smol_macros::test!)]
+    async fn include_cycles() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("main.journal");
+        let other = temp_dir.path().join("other.journal");
+        async_fs::write(&path, "include other.journal\n")
+            .await
+            .unwrap();
+        async_fs::write(&other, "include main.journal\n")
+            .await
+            .unwrap();
+        let result = Journal::load(&path).await;
+        assert!(matches!(
+            result,
+            Err(Error::IncludeCycle { path: cycle, includes })
+                if cycle == path && includes == vec![path.clone(), other.clone()]
+        ));
+
+        async_fs::write(&path, "include *.journal\n").await.unwrap();
+        async_fs::write(&other, "").await.unwrap();
+        let result = Journal::load(&path).await;
+        assert!(matches!(result, Err(Error::IncludeCycle { .. })));
+
+        async_fs::write(&path, "include other.journal\ninclude other.*\n")
+            .await
+            .unwrap();
+        let result = Journal::load(&path).await;
+        assert!(matches!(
+            result,
+            Err(Error::DuplicateInclude { includes, .. }) if includes == vec![path.clone()]
+        ));
+    }
+
+    #[apply(smol_macros::test!)]
+    async fn included_file_error() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("main.journal");
+        let other = temp_dir.path().join("other.journal");
+        async_fs::write(&path, "include other.journal\n")
+            .await
+            .unwrap();
+        async_fs::write(&other, "2024-01-01 test\n    a  $1 $2\n")
+            .await
+            .unwrap();
+        let result = Journal::load(&path).await;
+        assert!(matches!(
+            result,
+            Err(Error::File { path: file, includes, error })
+                if file == other && includes == vec![path.clone()]
+                    && matches!(*error, Error::Parse { .. })
+        ));
+    }
+
+    #[apply(smol_macros::test!)]
+    async fn errors_of_all_includes() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("main.journal");
+        async_fs::write(
+            &path,
+            "include a.journal\ninclude b.journal\ninclude c.journal\n",
+        )
+        .await
+        .unwrap();
+        async_fs::write(
+            temp_dir.path().join("a.journal"),
+            "2024-01-01 test\n    a  $1 $2\n",
+        )
+        .await
+        .unwrap();
+        async_fs::write(temp_dir.path().join("b.journal"), "")
+            .await
+            .unwrap();
+        async_fs::write(
+            temp_dir.path().join("c.journal"),
+            "2024-01-02 test\n    a  $1 $2\n",
+        )
+        .await
+        .unwrap();
+
+        let error = Journal::load(&path).await.unwrap_err();
+        let files = error
+            .errors()
+            .map(|error| match error {
+                Error::File { path, error, .. } => {
+                    assert!(
+                        matches!(&**error, Error::Parse { contents, .. } if contents.starts_with("2024"))
+                    );
+                    path.file_name().unwrap().to_str().unwrap()
+                }
+                error => panic!("unexpected error {error}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(files, vec!["a.journal", "c.journal"]);
+    }
+
+    #[apply(smol_macros::test!)]
+    async fn scope_of_includes() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("main.journal");
+        async_fs::write(
+            &path,
+            "year 2020\ndecimal-mark ,\nalias business = company\napply account business\ninclude other.journal\nend apply account\n\n01/03 after\n    checking  1,5\n    income\n",
+        )
+        .await
+        .unwrap();
+        async_fs::write(
+            temp_dir.path().join("other.journal"),
+            "year 2021\n01/02 inside\n    checking  2,5\n    income\n",
+        )
+        .await
+        .unwrap();
+
+        let journal = Journal::load(&path).await.unwrap();
+        let transactions = journal
+            .transactions()
+            .map(|(_, tx)| {
+                (
+                    tx.date.to_string(),
+                    tx.postings[0].account_name.to_string(),
+                    tx.postings[0].amount[0].quantity.to_string(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            transactions,
+            vec![
+                (
+                    String::from("2021-01-02"),
+                    String::from("company:checking"),
+                    String::from("2.5")
+                ),
+                (
+                    String::from("2020-01-03"),
+                    String::from("checking"),
+                    String::from("1.5")
+                ),
+            ]
+        );
+    }
+
+    #[apply(smol_macros::test!)]
+    async fn duplicate_transactions() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("main.journal");
+        async_fs::write(
+            &path,
+            "include bank.journal
+
+2024-01-02 Whole Foods
+    expenses:food  $10.00
+    assets:bank
+
+2024-01-02 Gas station
+    expenses:food  $10
+    assets:bank
+
+2024-02-02 Whole Foods
+    expenses:food  $10
+    assets:bank
+",
+        )
+        .await
+        .unwrap();
+        async_fs::write(
+            temp_dir.path().join("bank.journal"),
+            "2024-01-03 WHOLEFOODS MARKET
+    assets:bank  $-10
+    expenses:food  $10
+",
+        )
+        .await
+        .unwrap();
+
+        let journal = Journal::load(&path).await.unwrap();
+        let groups = journal
+            .duplicates(&Tolerance::new())
+            .into_iter()
+            .map(|group| {
+                group
+                    .into_iter()
+                    .map(|(path, tx)| {
+                        (
+                            path.file_name().unwrap().to_str().unwrap(),
+                            tx.payee.as_str(),
+                        )
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            groups,
+            vec![vec![
+                ("main.journal", "Whole Foods"),
+                ("bank.journal", "WHOLEFOODS MARKET"),
+            ]]
+        );
+
+        let groups = journal.duplicates(&Tolerance::new().with_days(0));
+        assert!(groups.is_empty());
+    }
+
+    #[apply(smol_macros::test!)]
+    async fn write_transactions() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("main.journal");
+        let other = temp_dir.path().join("other.journal");
+        async_fs::write(&path, "include other.journal\n")
+            .await
+            .unwrap();
+        async_fs::write(
+            &other,
+            "2024-01-01 first\n    a  $1\n    b\n\n2024-01-02 second\n    a  $2\n    b\n\n2024-01-03 third\n    a  $3\n    b\n",
+        )
+        .await
+        .unwrap();
+
+        let journal = Journal::load(&path).await.unwrap();
+        let (_, second) = journal.transactions().nth(1).unwrap();
+        let new = Transaction {
+            payee: String::from("new"),
+            ..second.clone()
+        };
+        journal
+            .replace_transaction(&other, second.position.clone(), &new)
+            .await
+            .unwrap();
+        assert_eq!(
+            async_fs::read_to_string(&other).await.unwrap(),
+            "2024-01-01 first\n    a  $1\n    b\n\n2024-01-02 new\n    a  $2\n    b\n\n2024-01-03 third\n    a  $3\n    b\n"
+        );
+
+        // the journal is out of date until it is loaded again
+        let result = journal
+            .delete_transaction(&other, second.position.clone())
+            .await;
+        let error = result.unwrap_err();
+        assert!(matches!(
+            &error,
+            Error::File { path: changed, includes, error }
+                if *changed == other && *includes == [path.clone()] && matches!(**error, Error::FileChanged)
+        ));
+
+        let journal = Journal::load(&path).await.unwrap();
+        let (_, second) = journal.transactions().nth(1).unwrap();
+        journal
+            .delete_transaction(&other, second.position.clone())
+            .await
+            .unwrap();
+        assert_eq!(
+            async_fs::read_to_string(&other).await.unwrap(),
+            "2024-01-01 first\n    a  $1\n    b\n\n2024-01-03 third\n    a  $3\n    b\n"
+        );
+
+        let journal = Journal::load(&path).await.unwrap();
+        journal.append_transaction(&path, &new).await.unwrap();
+        assert_eq!(
+            async_fs::read_to_string(&path).await.unwrap(),
+            "include other.journal\n\n2024-01-02 new\n    a  $2\n    b\n"
+        );
+
+        let error = journal.delete_transaction(&path, 0..1).await.unwrap_err();
+        assert!(matches!(
+            in_file(&error).1,
+            Error::TransactionNotFound { .. }
+        ));
+        let error = journal
+            .append_transaction(&temp_dir.path().join("unknown.journal"), &new)
+            .await
+            .unwrap_err();
+        assert!(matches!(in_file(&error), (file, Error::NotInJournal(_)) if file == path));
+    }
+
+    #[cfg(unix)]
+    #[apply(smol_macros::test!)]
+    async fn write_through_symlink() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let target = temp_dir.path().join("target.journal");
+        let path = temp_dir.path().join("main.journal");
+        async_fs::write(&target, "2024-01-01 first\n    a  $1\n    b\n")
+            .await
+            .unwrap();
+        std::os::unix::fs::symlink(&target, &path).unwrap();
+
+        let journal = Journal::load(&path).await.unwrap();
+        let (_, first) = journal.transactions().next().unwrap();
+        let new = Transaction {
+            payee: String::from("new"),
+            ..first.clone()
+        };
+        journal.append_transaction(&path, &new).await.unwrap();
+        assert!(async_fs::symlink_metadata(&path)
+            .await
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(
+            async_fs::read_to_string(&target).await.unwrap(),
+            "2024-01-01 first\n    a  $1\n    b\n\n2024-01-01 new\n    a  $1\n    b\n"
+        );
+    }
+
+    #[apply(smol_macros::test!)]
+    async fn transaction_ids() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("main.journal");
+        let other = temp_dir.path().join("other.journal");
+        async_fs::write(
+            &path,
+            "include other.journal\n\n2024-01-05 main\n    a  $1\n    b\n",
+        )
+        .await
+        .unwrap();
+        async_fs::write(&other, "2024-01-01 first\n    a  $1\n    b\n")
+            .await
+            .unwrap();
+
+        let journal = Journal::load(&path).await.unwrap();
+        let ids = journal
+            .transactions_with_ids()
+            .map(|(id, _, tx)| (id, tx.payee.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(ids.len(), 2);
+        assert_eq!(ids[0].1, "first");
+
+        async_fs::write(
+            &other,
+            "2023-12-31 new\n    a  $1\n    b\n\n2024-01-01 first\n    a  $1\n    b\n",
+        )
+        .await
+        .unwrap();
+        let journal = Journal::load(&path).await.unwrap();
+        for (id, payee) in ids {
+            let (_, tx) = journal.transaction(id).unwrap();
+            assert_eq!(tx.payee, payee);
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use hledger_parser::ParseOptions;
+
+/// Options that control how journals are loaded.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LoadOptions {
+    /// Options the journal and its includes are parsed with. Included files start from the
+    /// state of the file including them.
+    pub parse: ParseOptions,
+    /// Whether accounts and commodities must be declared, like `hledger --strict`.
+    pub strict: bool,
+    /// Whether auto posting rules add postings to matching transactions, like `hledger --auto`.
+    pub auto: bool,
+    /// Whether transactions that do not balance are kept as they are rather than failing the
+    /// load, so that [`Check::Balanced`](crate::Check::Balanced) reports them.
+    pub lenient: bool,
+}
+
+impl LoadOptions {
+    /// Options with defaults taken from the system clock.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the options the journal and its includes are parsed with.
+    #[must_use]
+    pub fn with_parse_options(mut self, parse: ParseOptions) -> Self {
+        self.parse = parse;
+        self
+    }
+
+    /// Sets whether accounts and commodities must be declared.
+    #[must_use]
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Sets whether auto posting rules add postings to matching transactions.
+    #[must_use]
+    pub fn with_auto(mut self, auto: bool) -> Self {
+        self.auto = auto;
+        self
+    }
+
+    /// Sets whether transactions that do not balance are kept as they are.
+    #[must_use]
+    pub fn with_lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+}
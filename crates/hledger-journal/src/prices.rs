@@ -0,0 +1,262 @@
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+
+use hledger_parser::{AmountPrice, Period};
+use rust_decimal::Decimal;
+
+use crate::{Amount, Journal};
+
+/// How amounts are valued.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Valuation {
+    /// At the cost they were bought for, the amount of their `@` or `@@` price, converted at the
+    /// latest market price. Amounts without a price are converted as they are.
+    Cost,
+    /// At the market price on the last day of the period.
+    End(Period),
+    /// At the market price on the date.
+    Date(chrono::NaiveDate),
+}
+
+/// Market prices of commodities, from `P` directives and, optionally, prices of postings.
+#[derive(Debug, Clone, Default)]
+pub struct Prices {
+    // rates from one commodity to another, sorted by date and in order of declaration within a
+    // date
+    rates: HashMap<String, HashMap<String, Vec<(chrono::NaiveDate, Decimal)>>>,
+}
+
+impl Prices {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Collects prices of the journal and its includes. With `implied`, prices of postings are
+    /// also used, dated with their transaction.
+    #[must_use]
+    pub fn from_journal(journal: &Journal, implied: bool) -> Self {
+        let mut prices = Self::new();
+        for (_, price) in journal.prices() {
+            prices.add(
+                price.date,
+                &price.commodity,
+                &price.amount.commodity,
+                price.amount.quantity,
+            );
+        }
+        if implied {
+            let amounts = journal.transactions().flat_map(|(_, transaction)| {
+                transaction
+                    .postings
+                    .iter()
+                    .flat_map(|posting| posting.amount.iter())
+                    .map(|amount| (transaction.date, amount))
+            });
+            for (date, amount) in amounts {
+                let rate = match amount.price.as_deref() {
+                    Some(AmountPrice::Unit(price)) => Some((price, price.quantity)),
+                    Some(AmountPrice::Total(price)) => price
+                        .quantity
+                        .checked_div(amount.quantity.abs())
+                        .map(|rate| (price, rate)),
+                    _ => None,
+                };
+                if let Some((price, rate)) = rate {
+                    prices.add(date, &amount.commodity, &price.commodity, rate);
+                }
+            }
+        }
+        prices
+    }
+
+    /// Adds the rate of one unit of `from` in `to` on the date. Rates added later take
+    /// precedence over the ones of the same date.
+    pub fn add(&mut self, date: chrono::NaiveDate, from: &str, to: &str, rate: Decimal) {
+        let rates = self
+            .rates
+            .entry(from.to_string())
+            .or_default()
+            .entry(to.to_string())
+            .or_default();
+        let index = rates.partition_point(|(rate_date, _)| *rate_date <= date);
+        rates.insert(index, (date, rate));
+    }
+
+    // latest declared rate on or before the date, or the inverse of the opposite rate
+    fn direct_rate(&self, from: &str, to: &str, date: chrono::NaiveDate) -> Option<Decimal> {
+        let latest = |from: &str, to: &str| {
+            let rates = self.rates.get(from)?.get(to)?;
+            let index = rates.partition_point(|(rate_date, _)| *rate_date <= date);
+            index.checked_sub(1).map(|index| rates[index].1)
+        };
+        latest(from, to).or_else(|| Decimal::ONE.checked_div(latest(to, from)?))
+    }
+
+    /// Returns the rate of one unit of `from` in `to` on the date.
+    ///
+    /// When there is no price between the two commodities, it is computed through intermediate
+    /// commodities, using the fewest conversions.
+    #[must_use]
+    pub fn rate(&self, from: &str, to: &str, date: chrono::NaiveDate) -> Option<Decimal> {
+        if from == to {
+            return Some(Decimal::ONE);
+        }
+        // commodities that have a price with each commodity, sorted for deterministic results
+        let mut neighbours = HashMap::<&str, BTreeSet<&str>>::new();
+        for (a, rates) in &self.rates {
+            for b in rates.keys() {
+                neighbours.entry(a).or_default().insert(b);
+                neighbours.entry(b).or_default().insert(a);
+            }
+        }
+
+        let mut visited = HashSet::from([from]);
+        let mut queue = VecDeque::from([(from, Decimal::ONE)]);
+        while let Some((commodity, rate)) = queue.pop_front() {
+            for next in neighbours.get(commodity).into_iter().flatten() {
+                if visited.contains(next) {
+                    continue;
+                }
+                let Some(next_rate) = self.direct_rate(commodity, next, date) else {
+                    continue;
+                };
+                let rate = rate.checked_mul(next_rate)?;
+                if *next == to {
+                    return Some(rate);
+                }
+                visited.insert(next);
+                queue.push_back((next, rate));
+            }
+        }
+        None
+    }
+
+    /// Values the amount in the commodity, or returns `None` when there is no price to do so.
+    #[must_use]
+    pub fn value(&self, amount: &Amount, commodity: &str, valuation: &Valuation) -> Option<Amount> {
+        let (amount, date) = match valuation {
+            Valuation::Cost if amount.price.is_some() => (amount.cost()?, chrono::NaiveDate::MAX),
+            Valuation::Cost => (amount.clone(), chrono::NaiveDate::MAX),
+            Valuation::End(period) => (amount.clone(), period.end?.pred_opt()?),
+            Valuation::Date(date) => (amount.clone(), *date),
+        };
+        let rate = self.rate(&amount.commodity, commodity, date)?;
+        Some(Amount {
+            quantity: amount.quantity.checked_mul(rate)?,
+            commodity: commodity.to_string(),
+            ..Amount::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(month: u32, day: u32) -> chrono::NaiveDate {
+        chrono::NaiveDate::from_ymd_opt(2024, month, day).unwrap()
+    }
+
+    fn prices() -> Prices {
+        let mut prices = Prices::new();
+        prices.add(date(1, 1), "EUR", "USD", Decimal::new(11, 1));
+        prices.add(date(2, 1), "EUR", "USD", Decimal::new(12, 1));
+        prices.add(date(2, 1), "EUR", "USD", Decimal::new(13, 1));
+        prices.add(date(1, 1), "AAPL", "USD", Decimal::new(150, 0));
+        prices
+    }
+
+    #[test]
+    fn latest_rate() {
+        let prices = prices();
+        assert_eq!(
+            prices.rate("EUR", "USD", date(1, 15)),
+            Some(Decimal::new(11, 1))
+        );
+        assert_eq!(
+            prices.rate("EUR", "USD", date(3, 1)),
+            Some(Decimal::new(13, 1))
+        );
+        assert_eq!(
+            prices.rate("EUR", "USD", date(1, 1).pred_opt().unwrap()),
+            None
+        );
+        assert_eq!(prices.rate("USD", "USD", date(1, 1)), Some(Decimal::ONE));
+    }
+
+    #[test]
+    fn chained_rate() {
+        let prices = prices();
+        assert_eq!(
+            prices.rate("AAPL", "EUR", date(3, 1)),
+            Some(Decimal::new(150, 0) / Decimal::new(13, 1))
+        );
+        assert_eq!(prices.rate("AAPL", "GBP", date(3, 1)), None);
+    }
+
+    #[test]
+    fn zero_rate() {
+        let mut prices = Prices::new();
+        prices.add(date(1, 1), "X", "Y", Decimal::ZERO);
+        assert_eq!(prices.rate("X", "Y", date(1, 1)), Some(Decimal::ZERO));
+        assert_eq!(prices.rate("Y", "X", date(1, 1)), None);
+    }
+
+    #[test]
+    fn valuation() {
+        let mut prices = prices();
+        prices.add(date(1, 1), "GBP", "USD", Decimal::new(125, 2));
+        let amount = Amount {
+            quantity: Decimal::new(2, 0),
+            commodity: String::from("EUR"),
+            price: Some(Box::new(AmountPrice::Unit(Amount {
+                quantity: Decimal::new(1, 0),
+                commodity: String::from("GBP"),
+                ..Amount::default()
+            }))),
+            ..Amount::default()
+        };
+        let value = |valuation| {
+            prices
+                .value(&amount, "USD", &valuation)
+                .map(|amount| (amount.quantity, amount.commodity))
+        };
+        assert_eq!(
+            value(Valuation::Cost),
+            Some((Decimal::new(250, 2), String::from("USD")))
+        );
+        assert!(prices.value(&amount, "JPY", &Valuation::Cost).is_none());
+        assert_eq!(
+            value(Valuation::End(Period {
+                begin: Some(date(1, 1)),
+                end: Some(date(2, 1)),
+            })),
+            Some((Decimal::new(22, 1), String::from("USD")))
+        );
+        assert_eq!(
+            value(Valuation::Date(date(2, 1))),
+            Some((Decimal::new(26, 1), String::from("USD")))
+        );
+    }
+
+    #[test]
+    fn implied_prices() {
+        let journal = Journal::from_contents(
+            "P 2024-01-01 EUR $1.10
+
+2024-01-05 buy
+    assets:stock  10 AAPL @@ 1500 EUR
+    assets:cash",
+        );
+        let date = chrono::NaiveDate::from_ymd_opt(2024, 1, 10).unwrap();
+
+        let prices = Prices::from_journal(&journal, false);
+        assert_eq!(prices.rate("AAPL", "$", date), None);
+
+        let prices = Prices::from_journal(&journal, true);
+        assert_eq!(
+            prices.rate("AAPL", "$", date),
+            Some(rust_decimal::Decimal::new(165, 0))
+        );
+    }
+}
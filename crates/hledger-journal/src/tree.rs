@@ -0,0 +1,238 @@
+use std::collections::BTreeMap;
+
+use rust_decimal::Decimal;
+
+use crate::{Amount, Journal};
+
+/// Account hierarchy with balances per commodity.
+///
+/// Accounts are ordered by declaration, then alphabetically. An account that is not declared
+/// itself is placed with the first declared of its subaccounts.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AccountTree {
+    /// Last part of the account name, empty for the root.
+    pub name: String,
+    /// Full account name, empty for the root.
+    pub full_name: String,
+    /// Balance of the postings to the account itself.
+    pub exclusive: BTreeMap<String, Decimal>,
+    /// Balance of the postings to the account and its subaccounts.
+    pub inclusive: BTreeMap<String, Decimal>,
+    pub children: Vec<AccountTree>,
+    // position of the account, or of its first declared subaccount, among declarations
+    order: Option<usize>,
+}
+
+impl AccountTree {
+    /// Builds the tree from account declarations, in order, and posted amounts.
+    #[must_use]
+    pub fn new<'a>(
+        declared: impl IntoIterator<Item = String>,
+        postings: impl IntoIterator<Item = (String, &'a Amount)>,
+    ) -> Self {
+        let mut root = Self::default();
+        for (order, account) in declared.into_iter().enumerate() {
+            let node = root.entry(&account);
+            node.order = node.order.or(Some(order));
+        }
+        for (account, amount) in postings {
+            *root
+                .entry(&account)
+                .exclusive
+                .entry(amount.commodity.clone())
+                .or_default() += amount.quantity;
+        }
+        root.aggregate();
+        root
+    }
+
+    /// Builds the tree of the journal and its includes.
+    #[must_use]
+    pub fn from_journal(journal: &Journal) -> Self {
+        let declared = journal
+            .accounts()
+            .map(|(_, account)| account.account_name.to_string());
+        let postings = journal.transactions().flat_map(|(_, transaction)| {
+            transaction.postings.iter().flat_map(|posting| {
+                let account = posting.account_name.to_string();
+                posting
+                    .amount
+                    .iter()
+                    .map(move |amount| (account.clone(), amount))
+            })
+        });
+        Self::new(declared, postings)
+    }
+
+    fn entry(&mut self, account: &str) -> &mut Self {
+        let mut node = self;
+        for part in account.split(':') {
+            let index = if let Some(index) = node.children.iter().position(|c| c.name == part) {
+                index
+            } else {
+                node.children.push(Self {
+                    name: part.to_string(),
+                    full_name: if node.full_name.is_empty() {
+                        part.to_string()
+                    } else {
+                        format!("{}:{part}", node.full_name)
+                    },
+                    ..Self::default()
+                });
+                node.children.len() - 1
+            };
+            node = &mut node.children[index];
+        }
+        node
+    }
+
+    // computes inclusive balances and orders children
+    fn aggregate(&mut self) {
+        self.inclusive.clone_from(&self.exclusive);
+        for child in &mut self.children {
+            child.aggregate();
+            for (commodity, quantity) in &child.inclusive {
+                *self.inclusive.entry(commodity.clone()).or_default() += quantity;
+            }
+            self.order = match (self.order, child.order) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            };
+        }
+        self.children.sort_by(|a, b| {
+            let order = |tree: &Self| tree.order.unwrap_or(usize::MAX);
+            order(a).cmp(&order(b)).then_with(|| a.name.cmp(&b.name))
+        });
+    }
+
+    /// Returns the account with the full name.
+    #[must_use]
+    pub fn find(&self, account: &str) -> Option<&Self> {
+        account.split(':').try_fold(self, |node, part| {
+            node.children.iter().find(|child| child.name == part)
+        })
+    }
+
+    /// Returns the tree without accounts deeper than `depth`. Clipped accounts are summarized in
+    /// their parent, so its exclusive balance becomes its inclusive one.
+    #[must_use]
+    pub fn clip(&self, depth: usize) -> Self {
+        if depth == 0 {
+            return Self {
+                exclusive: self.inclusive.clone(),
+                children: Vec::new(),
+                ..self.clone()
+            };
+        }
+        Self {
+            children: self
+                .children
+                .iter()
+                .map(|child| child.clip(depth - 1))
+                .collect(),
+            ..self.clone()
+        }
+    }
+
+    /// Returns every account of the tree in order, depth first, with its depth.
+    #[must_use]
+    pub fn accounts(&self) -> Vec<(usize, &Self)> {
+        let mut accounts = Vec::new();
+        let mut stack = self
+            .children
+            .iter()
+            .rev()
+            .map(|child| (1, child))
+            .collect::<Vec<_>>();
+        while let Some((depth, node)) = stack.pop() {
+            accounts.push((depth, node));
+            stack.extend(node.children.iter().rev().map(|child| (depth + 1, child)));
+        }
+        accounts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn amount(quantity: i64, commodity: &str) -> Amount {
+        Amount {
+            quantity: Decimal::from(quantity),
+            commodity: String::from(commodity),
+            ..Amount::default()
+        }
+    }
+
+    fn tree() -> AccountTree {
+        let amounts = [
+            amount(10, "$"),
+            amount(5, "EUR"),
+            amount(-15, "$"),
+            amount(3, "$"),
+        ];
+        AccountTree::new(
+            [String::from("income"), String::from("assets:cash")],
+            [
+                (String::from("expenses:food"), &amounts[0]),
+                (String::from("assets:bank"), &amounts[1]),
+                (String::from("income"), &amounts[2]),
+                (String::from("assets"), &amounts[3]),
+            ],
+        )
+    }
+
+    #[test]
+    fn ordering() {
+        let tree = tree();
+        let names = tree
+            .accounts()
+            .into_iter()
+            .map(|(depth, account)| (depth, account.full_name.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                (1, "income"),
+                (1, "assets"),
+                (2, "assets:cash"),
+                (2, "assets:bank"),
+                (1, "expenses"),
+                (2, "expenses:food"),
+            ]
+        );
+    }
+
+    #[test]
+    fn balances() {
+        let tree = tree();
+        let assets = tree.find("assets").unwrap();
+        assert_eq!(
+            assets.exclusive,
+            BTreeMap::from([(String::from("$"), Decimal::from(3))])
+        );
+        assert_eq!(
+            assets.inclusive,
+            BTreeMap::from([
+                (String::from("$"), Decimal::from(3)),
+                (String::from("EUR"), Decimal::from(5)),
+            ])
+        );
+        assert_eq!(
+            tree.inclusive,
+            BTreeMap::from([
+                (String::from("$"), Decimal::from(-2)),
+                (String::from("EUR"), Decimal::from(5)),
+            ])
+        );
+    }
+
+    #[test]
+    fn clip() {
+        let tree = tree().clip(1);
+        assert_eq!(tree.accounts().len(), 3);
+        let assets = tree.find("assets").unwrap();
+        assert!(assets.children.is_empty());
+        assert_eq!(assets.exclusive, assets.inclusive);
+    }
+}
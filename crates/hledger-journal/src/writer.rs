@@ -0,0 +1,315 @@
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use hledger_parser::{AmountPrice, Directive, Status};
+
+use crate::{Amount, Error, Journal, Posting, Transaction};
+
+// journal of the file, with the files from the root journal to the one including it
+fn find<'a>(journal: &'a Journal, path: &Path) -> Option<(Vec<PathBuf>, &'a Journal)> {
+    if journal.path == path {
+        return Some((Vec::new(), journal));
+    }
+    journal.includes.iter().find_map(|(_, included)| {
+        find(included, path).map(|(mut includes, found)| {
+            includes.insert(0, journal.path.clone());
+            (includes, found)
+        })
+    })
+}
+
+// like `find`, a file that is not part of the journal is an error of the root journal
+fn find_file<'a>(journal: &'a Journal, path: &Path) -> Result<(Vec<PathBuf>, &'a Journal), Error> {
+    find(journal, path).ok_or_else(|| Error::File {
+        path: journal.path.clone(),
+        includes: Vec::new(),
+        error: Box::new(Error::NotInJournal(path.to_path_buf())),
+    })
+}
+
+// checks that the file of the journal has a transaction at the position
+fn check_position(
+    journal: &Journal,
+    path: &Path,
+    position: &std::ops::Range<usize>,
+) -> Result<(), Error> {
+    let (includes, file) = find_file(journal, path)?;
+    let is_transaction = file.directives.iter().any(|directive| {
+        matches!(directive, Directive::Transaction(transaction) if transaction.position == *position)
+    });
+    if is_transaction {
+        Ok(())
+    } else {
+        Err(Error::File {
+            path: path.to_path_buf(),
+            includes,
+            error: Box::new(Error::TransactionNotFound {
+                position: position.clone(),
+            }),
+        })
+    }
+}
+
+// replaces the file with the edited contents, if it did not change since it was loaded
+async fn write(
+    journal: &Journal,
+    path: &Path,
+    edit: impl FnOnce(&str) -> String,
+) -> Result<(), Error> {
+    let (includes, file) = find_file(journal, path)?;
+    let in_file = |error| Error::File {
+        path: path.to_path_buf(),
+        includes: includes.clone(),
+        error: Box::new(error),
+    };
+
+    let contents = async_fs::read_to_string(path)
+        .await
+        .map_err(|error| in_file(Error::Io(Arc::new(error))))?;
+    if *contents != *file.contents {
+        return Err(in_file(Error::FileChanged));
+    }
+    let contents = edit(&contents);
+
+    // the file a symbolic link points to is replaced, not the link
+    let path = async_fs::canonicalize(path)
+        .await
+        .map_err(|error| in_file(Error::Io(Arc::new(error))))?;
+    // written next to the file, so that the rename does not cross file systems
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temp_path = path.with_file_name(format!(".{name}.{}.tmp", std::process::id()));
+    let result = async {
+        async_fs::write(&temp_path, contents).await?;
+        let permissions = async_fs::metadata(&path).await?.permissions();
+        async_fs::set_permissions(&temp_path, permissions).await?;
+        async_fs::rename(&temp_path, &path).await
+    }
+    .await;
+    if let Err(error) = result {
+        let _ = async_fs::remove_file(&temp_path).await;
+        return Err(in_file(Error::Io(Arc::new(error))));
+    }
+    Ok(())
+}
+
+pub(crate) async fn append(
+    journal: &Journal,
+    path: &Path,
+    transaction: &Transaction,
+) -> Result<(), Error> {
+    let text = format(transaction);
+    write(journal, path, |contents| {
+        // separated from the previous entry by an empty line
+        let separator = match contents {
+            "" => "",
+            contents if contents.ends_with("\n\n") => "",
+            contents if contents.ends_with('\n') => "\n",
+            _ => "\n\n",
+        };
+        format!("{contents}{separator}{text}\n")
+    })
+    .await
+}
+
+pub(crate) async fn replace(
+    journal: &Journal,
+    path: &Path,
+    position: std::ops::Range<usize>,
+    transaction: &Transaction,
+) -> Result<(), Error> {
+    check_position(journal, path, &position)?;
+    let text = format(transaction);
+    write(journal, path, |contents| {
+        format!(
+            "{}{text}{}",
+            &contents[..position.start],
+            &contents[position.end..]
+        )
+    })
+    .await
+}
+
+pub(crate) async fn delete(
+    journal: &Journal,
+    path: &Path,
+    position: std::ops::Range<usize>,
+) -> Result<(), Error> {
+    check_position(journal, path, &position)?;
+    write(journal, path, |contents| {
+        // the line break ending the transaction goes with it, and so does the empty line after it
+        // when there is one before it too
+        let (before, mut after) = (&contents[..position.start], &contents[position.end..]);
+        if let Some(rest) = after.strip_prefix('\n') {
+            after = rest;
+            if before.is_empty() || before.ends_with("\n\n") {
+                after = after.strip_prefix('\n').unwrap_or(after);
+            }
+        }
+        format!("{before}{after}")
+    })
+    .await
+}
+
+// commodities that are symbols go before the quantity, others after it, quoted when they contain
+// anything but letters
+fn format_amount(amount: &Amount) -> String {
+    let quantity = amount.style.format(&amount.quantity);
+    let commodity = &amount.commodity;
+    let mut text = if commodity.is_empty() {
+        quantity
+    } else if commodity.chars().count() == 1 && !commodity.chars().all(char::is_alphanumeric) {
+        format!("{commodity}{quantity}")
+    } else if commodity.chars().all(char::is_alphabetic) {
+        format!("{quantity} {commodity}")
+    } else {
+        format!("{quantity} \"{commodity}\"")
+    };
+    match amount.price.as_deref() {
+        Some(AmountPrice::Unit(price)) => write!(text, " @ {}", format_amount(price)),
+        Some(AmountPrice::Total(price)) => write!(text, " @@ {}", format_amount(price)),
+        None => Ok(()),
+    }
+    .expect("writing to a string");
+    text
+}
+
+fn format_status(status: Option<&Status>) -> &'static str {
+    match status {
+        Some(Status::Pending) => "! ",
+        Some(Status::Cleared) => "* ",
+        None => "",
+    }
+}
+
+fn format_tags(tags: &[(String, String)]) -> String {
+    if tags.is_empty() {
+        return String::new();
+    }
+    let tags = tags
+        .iter()
+        .map(|(name, value)| format!("{name}: {value}"))
+        .collect::<Vec<_>>();
+    format!("  ; {}", tags.join(", "))
+}
+
+// comments are written as they were parsed, with their tags, continuation lines indented by
+// `indent`; tags are only written on their own when there is no comment
+fn format_comment(comment: Option<&str>, tags: &[(String, String)], indent: &str) -> String {
+    let Some(comment) = comment else {
+        return format_tags(tags);
+    };
+    let lines = comment
+        .lines()
+        .map(|line| format!(";{line}"))
+        .collect::<Vec<_>>();
+    format!("  {}", lines.join(&format!("\n{indent}")))
+}
+
+fn format_posting(posting: &Posting) -> String {
+    let account = if posting.is_virtual {
+        format!("({})", posting.account_name)
+    } else if posting.is_balanced_virtual {
+        format!("[{}]", posting.account_name)
+    } else {
+        posting.account_name.to_string()
+    };
+    let mut line = format!("    {}{account}", format_status(posting.status.as_ref()));
+    if posting.is_amount_specified {
+        for amount in &posting.amount {
+            write!(line, "  {}", format_amount(amount)).expect("writing to a string");
+        }
+    }
+    if let Some(assertion) = &posting.assertion {
+        let separator = if posting.is_amount_specified {
+            " "
+        } else {
+            "  "
+        };
+        write!(
+            line,
+            "{separator}{}{} {}",
+            if assertion.is_strict { "==" } else { "=" },
+            if assertion.is_subaccount_inclusive {
+                "*"
+            } else {
+                ""
+            },
+            format_amount(&assertion.amount)
+        )
+        .expect("writing to a string");
+    }
+    line + &format_comment(posting.comment.as_deref(), &posting.tags, "      ")
+}
+
+/// Formats the transaction as journal text, without a final line break.
+///
+/// Amounts are only written for postings with `is_amount_specified`, others are inferred again
+/// when the journal is loaded. Postings added by auto posting rules are left out. Comments are
+/// written as they are, so tags changed without changing the comment are not written.
+pub(crate) fn format(transaction: &Transaction) -> String {
+    let mut header = transaction.date.format("%Y-%m-%d").to_string();
+    let status = format_status(transaction.status.as_ref()).trim_end();
+    if !status.is_empty() {
+        write!(header, " {status}").expect("writing to a string");
+    }
+    if let Some(code) = &transaction.code {
+        write!(header, " ({code})").expect("writing to a string");
+    }
+    if !transaction.payee.is_empty() {
+        write!(header, " {}", transaction.payee).expect("writing to a string");
+    }
+    if let Some(note) = &transaction.note {
+        write!(header, " | {}", note.trim_end()).expect("writing to a string");
+    }
+    header.push_str(&format_comment(
+        transaction.comment.as_deref(),
+        &transaction.tags,
+        "    ",
+    ));
+    std::iter::once(header)
+        .chain(
+            transaction
+                .postings
+                .iter()
+                .filter(|posting| !posting.is_generated)
+                .map(format_posting),
+        )
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_round_trip() {
+        let text = "2024-01-02 * (42) Shop | weekly  ; trip: paris
+    ! expenses:food  12.50 EUR @ $1.10  ; type: groceries
+    (budget:food)  -10 \"FOOD 1\"
+    [savings]  $1,000.00
+    assets:cash  = $100
+    assets:bank  $-1 ==* $0
+    equity";
+        let directives = hledger_parser::parse(text).unwrap();
+        let Directive::Transaction(transaction) = &directives[0] else {
+            panic!("not a transaction");
+        };
+        assert_eq!(format(transaction), text);
+    }
+
+    #[test]
+    fn format_comments() {
+        let text = "2024-01-02 Shop  ; bought on the way home, trip: paris
+    ; second line of the transaction comment
+    expenses:food  $10  ; not a tag
+      ; second line of the posting comment
+    assets:cash";
+        let directives = hledger_parser::parse(text).unwrap();
+        let Directive::Transaction(transaction) = &directives[0] else {
+            panic!("not a transaction");
+        };
+        assert_eq!(format(transaction), text);
+    }
+}
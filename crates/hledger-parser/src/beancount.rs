@@ -0,0 +1,442 @@
+//! Parser for [Beancount](https://beancount.github.io/docs/beancount_language_syntax.html)
+//! ledgers, converting them into hledger directives.
+
+mod component;
+mod transaction;
+
+use std::collections::HashMap;
+
+use chumsky::prelude::*;
+
+use crate::beancount::component::{account, amount, commodity, metadata, string};
+use crate::beancount::transaction::transaction;
+use crate::component::account_name::AccountName;
+use crate::component::amount::Amount;
+use crate::component::comment::inline;
+use crate::component::date::simple::date;
+use crate::component::whitespace::whitespace;
+use crate::directive::{
+    Account, Assertion, Commodity, Directive, Include, Posting, Price, Transaction, Unsupported,
+};
+use crate::state::State;
+use crate::utils::end_of_line;
+
+enum Entry {
+    Directive(Directive),
+    Pad {
+        date: chrono::NaiveDate,
+        account: AccountName,
+        source: AccountName,
+    },
+    Balance {
+        date: chrono::NaiveDate,
+        account: AccountName,
+        amount: Amount,
+        position: std::ops::Range<usize>,
+        tags: Vec<(String, String)>,
+    },
+}
+
+// what follows the date of a dated entry
+enum Body {
+    Directive(Directive),
+    Price(String, Amount),
+    Balance(AccountName, Amount),
+    Pad(AccountName, AccountName),
+}
+
+// metadata lines that may follow any dated entry
+fn entry_metadata<'a>(
+) -> impl Parser<'a, &'a str, Vec<(String, String)>, extra::Full<Rich<'a, char>, State, ()>> {
+    text::newline()
+        .ignore_then(whitespace().repeated().at_least(1))
+        .ignore_then(metadata())
+        .then_ignore(end_of_line())
+        .repeated()
+        .collect()
+}
+
+fn rest_of_line<'a>() -> impl Parser<'a, &'a str, (), extra::Full<Rich<'a, char>, State, ()>> {
+    any().and_is(text::newline().not()).repeated()
+}
+
+fn dated<'a>() -> impl Parser<'a, &'a str, Entry, extra::Full<Rich<'a, char>, State, ()>> {
+    let separator = || whitespace().repeated().at_least(1);
+    let open = just("open")
+        .ignore_then(separator())
+        .ignore_then(account())
+        .then_ignore(
+            separator()
+                .ignore_then(
+                    commodity()
+                        .separated_by(just(",").padded_by(whitespace().repeated()))
+                        .at_least(1),
+                )
+                .or_not(),
+        )
+        .then_ignore(separator().ignore_then(string()).or_not())
+        .map(|account_name| Directive::Account(Account { account_name }));
+    let declare_commodity = just("commodity")
+        .ignore_then(separator())
+        .ignore_then(commodity())
+        .map(|commodity| Directive::Commodity(Commodity::Commodity(commodity)));
+    let price = just("price")
+        .ignore_then(separator())
+        .ignore_then(commodity())
+        .then_ignore(separator())
+        .then(amount());
+    let balance = just("balance")
+        .ignore_then(separator())
+        .ignore_then(account())
+        .then_ignore(separator())
+        .then(amount());
+    let pad = just("pad")
+        .ignore_then(separator())
+        .ignore_then(account())
+        .then_ignore(separator())
+        .then(account());
+    let unsupported = choice((
+        just("close"),
+        just("event"),
+        just("note"),
+        just("document"),
+        just("query"),
+        just("custom"),
+    ))
+    .then_ignore(rest_of_line())
+    .map_with(|directive, e| {
+        Directive::Unsupported(Unsupported {
+            directive: directive.to_string(),
+            position: e.span().into_range(),
+        })
+    });
+
+    let entry = date()
+        .then_ignore(separator())
+        .then(choice((
+            open.map(Body::Directive),
+            declare_commodity.map(Body::Directive),
+            price.map(|(commodity, amount)| Body::Price(commodity, amount)),
+            balance.map(|(account, amount)| Body::Balance(account, amount)),
+            pad.map(|(account, source)| Body::Pad(account, source)),
+            unsupported.map(Body::Directive),
+        )))
+        .then_ignore(end_of_line())
+        .map_with(|(date, body), e| match body {
+            Body::Directive(directive) => Entry::Directive(directive),
+            Body::Price(commodity, amount) => Entry::Directive(Directive::Price(Price {
+                date,
+                commodity,
+                amount,
+            })),
+            Body::Balance(account, amount) => Entry::Balance {
+                date,
+                account,
+                amount,
+                position: e.span().into_range(),
+                tags: Vec::new(),
+            },
+            Body::Pad(account, source) => Entry::Pad {
+                date,
+                account,
+                source,
+            },
+        });
+
+    // only balances become entries with tags, other directives have nowhere to keep metadata
+    transaction()
+        .map(|transaction| Entry::Directive(Directive::Transaction(transaction)))
+        .or(entry)
+        .then(entry_metadata())
+        .map(|(mut entry, metadata)| {
+            if let Entry::Balance { tags, .. } = &mut entry {
+                *tags = metadata;
+            }
+            entry
+        })
+}
+
+fn undated<'a>() -> impl Parser<'a, &'a str, Entry, extra::Full<Rich<'a, char>, State, ()>> {
+    let include = just("include")
+        .ignore_then(whitespace().repeated().at_least(1))
+        .ignore_then(string())
+        .then_ignore(end_of_line())
+        .map(|path| {
+            Directive::Include(Include {
+                format: None,
+                path: std::path::PathBuf::from(path),
+            })
+        });
+    let unsupported = choice((
+        just("option"),
+        just("plugin"),
+        just("pushtag"),
+        just("poptag"),
+        just("pushmeta"),
+        just("popmeta"),
+    ))
+    .then_ignore(rest_of_line())
+    .map_with(|directive, e| {
+        Directive::Unsupported(Unsupported {
+            directive: directive.to_string(),
+            position: e.span().into_range(),
+        })
+    });
+    include.or(unsupported).map(Entry::Directive)
+}
+
+fn entries<'a>() -> impl Parser<'a, &'a str, Vec<Entry>, extra::Full<Rich<'a, char>, State, ()>> {
+    // org-mode headings are allowed to structure the file
+    let heading = just("*").then(rest_of_line()).ignored();
+    choice((
+        dated().map(Some),
+        undated().map(Some),
+        heading.map(|()| None),
+        inline().map(|_| None),
+        whitespace().repeated().map(|()| None),
+    ))
+    .separated_by(text::newline())
+    .collect::<Vec<_>>()
+    .map(|entries| entries.into_iter().flatten().collect())
+}
+
+// A balance checks the balance at the beginning of its date, which is the end of the previous
+// day in hledger. It becomes a transaction with an assertion on that day, and when the account
+// was padded before, with a balance assignment balanced by the pad's source account.
+//
+// These transactions are placed after all other directives, so that they follow every other
+// transaction of the same day.
+fn convert(entries: Vec<Entry>) -> Vec<Directive> {
+    let mut balances = entries
+        .iter()
+        .filter_map(|entry| match entry {
+            Entry::Pad { date, .. } | Entry::Balance { date, .. } => Some((*date, entry)),
+            Entry::Directive(_) => None,
+        })
+        .collect::<Vec<_>>();
+    // on the same day, balances are checked before pads are applied
+    balances.sort_by_key(|(date, entry)| (*date, matches!(entry, Entry::Pad { .. })));
+
+    let mut pads = HashMap::new();
+    let mut assertions = Vec::new();
+    for (_, entry) in balances {
+        match entry {
+            Entry::Pad {
+                account, source, ..
+            } => {
+                pads.insert(account.to_string(), source.clone());
+            }
+            Entry::Balance {
+                date,
+                account,
+                amount,
+                position,
+                tags,
+            } => {
+                let assertion = Assertion {
+                    is_strict: false,
+                    is_subaccount_inclusive: true,
+                    amount: amount.clone(),
+                };
+                let posting = Posting {
+                    status: None,
+                    account_name: account.clone(),
+                    is_virtual: false,
+                    is_balanced_virtual: false,
+                    is_generated: false,
+                    comment: None,
+                    tags: Vec::new(),
+                    amount: Vec::new(),
+                    is_amount_specified: false,
+                    assertion: Some(assertion),
+                    position: position.clone(),
+                };
+                let postings = if let Some(source) = pads.remove(&account.to_string()) {
+                    vec![
+                        posting.clone(),
+                        Posting {
+                            account_name: source,
+                            assertion: None,
+                            ..posting
+                        },
+                    ]
+                } else {
+                    vec![Posting {
+                        amount: vec![Amount {
+                            quantity: rust_decimal::Decimal::ZERO,
+                            commodity: amount.commodity.clone(),
+                            price: None,
+                            style: amount.style.clone(),
+                        }],
+                        is_amount_specified: true,
+                        ..posting
+                    }]
+                };
+                assertions.push(Directive::Transaction(Transaction {
+                    date: date.pred_opt().unwrap_or(*date),
+                    status: None,
+                    code: None,
+                    payee: String::from("balance"),
+                    note: None,
+                    postings,
+                    position: position.clone(),
+                    is_generated: false,
+                    comment: None,
+                    tags: tags.clone(),
+                }));
+            }
+            Entry::Directive(_) => {}
+        }
+    }
+
+    entries
+        .into_iter()
+        .filter_map(|entry| match entry {
+            Entry::Directive(directive) => Some(directive),
+            Entry::Pad { .. } | Entry::Balance { .. } => None,
+        })
+        .chain(assertions)
+        .collect()
+}
+
+pub fn directives<'a>(
+) -> impl Parser<'a, &'a str, Vec<Directive>, extra::Full<Rich<'a, char>, State, ()>> {
+    entries().map(convert)
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal::Decimal;
+
+    use super::*;
+
+    fn parse(contents: &str) -> Vec<Directive> {
+        directives()
+            .then_ignore(end())
+            .parse(contents)
+            .into_result()
+            .unwrap()
+    }
+
+    #[test]
+    fn full() {
+        let result = parse(
+            "option \"title\" \"Example\"
+* Accounts
+2014-01-01 open Assets:Checking USD,EUR \"STRICT\"
+  description: \"main account\"
+2014-01-01 commodity USD
+2014-02-01 close Assets:Checking
+include \"other.beancount\"
+; comment
+2014-05-05 price HOOL 510.00 USD
+
+2014-05-05 * \"Deposit\"
+  Assets:Checking  100 USD
+  Income:Salary",
+        );
+        assert_eq!(result.len(), 7);
+        assert_eq!(
+            result[0],
+            Directive::Unsupported(Unsupported {
+                directive: String::from("option"),
+                position: 0..24,
+            })
+        );
+        assert_eq!(
+            result[1],
+            Directive::Account(Account {
+                account_name: AccountName::from_parts(&[
+                    String::from("Assets"),
+                    String::from("Checking"),
+                ]),
+            })
+        );
+        assert_eq!(
+            result[2],
+            Directive::Commodity(Commodity::Commodity(String::from("USD")))
+        );
+        assert!(matches!(result[3], Directive::Unsupported(_)));
+        assert_eq!(
+            result[4],
+            Directive::Include(Include {
+                format: None,
+                path: std::path::PathBuf::from("other.beancount"),
+            })
+        );
+        assert_eq!(
+            result[5],
+            Directive::Price(Price {
+                date: chrono::NaiveDate::from_ymd_opt(2014, 5, 5).unwrap(),
+                commodity: String::from("HOOL"),
+                amount: Amount {
+                    quantity: Decimal::new(51000, 2),
+                    commodity: String::from("USD"),
+                    price: None,
+                    ..Amount::default()
+                },
+            })
+        );
+        assert!(matches!(result[6], Directive::Transaction(_)));
+    }
+
+    #[test]
+    fn balance() {
+        let result = parse(
+            "2014-08-09 balance Assets:Checking  100.00 ~ 0.01 USD
+  statement: \"august.pdf\"
+2014-08-08 * \"Deposit\"
+  Assets:Checking  100 USD
+  Income:Salary",
+        );
+        let Some(Directive::Transaction(balance)) = result.last() else {
+            panic!("expected balance transaction");
+        };
+        assert_eq!(
+            balance.date,
+            chrono::NaiveDate::from_ymd_opt(2014, 8, 8).unwrap()
+        );
+        assert_eq!(balance.postings.len(), 1);
+        assert_eq!(
+            balance.tags,
+            vec![(String::from("statement"), String::from("august.pdf"))]
+        );
+        assert_eq!(
+            balance.postings[0]
+                .assertion
+                .as_ref()
+                .map(|a| a.amount.clone()),
+            Some(Amount {
+                quantity: Decimal::new(10000, 2),
+                commodity: String::from("USD"),
+                price: None,
+                ..Amount::default()
+            })
+        );
+    }
+
+    #[test]
+    fn pad() {
+        let result = parse(
+            "2014-01-01 pad Assets:Checking Equity:Opening-Balances
+2014-01-01 balance Assets:Checking 5 USD
+2014-02-01 balance Assets:Checking 10 USD",
+        );
+        let transactions = result
+            .iter()
+            .filter_map(|directive| match directive {
+                Directive::Transaction(transaction) => Some(transaction),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(transactions.len(), 2);
+        // the balance on the pad's date is checked before padding
+        assert_eq!(transactions[0].postings.len(), 1);
+        assert_eq!(transactions[1].postings.len(), 2);
+        assert!(!transactions[1].postings[0].is_amount_specified);
+        assert_eq!(
+            transactions[1].postings[1].account_name.to_string(),
+            "Equity:Opening-Balances"
+        );
+    }
+}
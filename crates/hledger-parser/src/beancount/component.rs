@@ -0,0 +1,237 @@
+use std::str::FromStr;
+
+use chumsky::prelude::*;
+use rust_decimal::Decimal;
+
+use crate::component::account_name::AccountName;
+use crate::component::amount::Amount;
+use crate::component::date::simple::date;
+use crate::component::price::AmountPrice;
+use crate::component::quantity::Style;
+use crate::component::whitespace::whitespace;
+use crate::state::State;
+
+// Assets:Cash, Liabilities:US:Chase-Card
+pub fn account<'a>() -> impl Parser<'a, &'a str, AccountName, extra::Full<Rich<'a, char>, State, ()>>
+{
+    let first = any().filter(|c: &char| c.is_uppercase() || c.is_ascii_digit());
+    let rest = any().filter(|c: &char| c.is_alphanumeric() || *c == '-');
+    first
+        .then(rest.repeated().collect::<String>())
+        .map(|(first, rest)| format!("{first}{rest}"))
+        .separated_by(just(":"))
+        .at_least(2)
+        .collect::<Vec<_>>()
+        .map(|parts| AccountName::from_parts(&parts))
+}
+
+// USD, VACHR, HOOL.B
+pub fn commodity<'a>() -> impl Parser<'a, &'a str, String, extra::Full<Rich<'a, char>, State, ()>> {
+    let first = any().filter(|c: &char| c.is_ascii_uppercase() || *c == '/');
+    let rest = any()
+        .filter(|c: &char| c.is_ascii_uppercase() || c.is_ascii_digit() || "'._-/".contains(*c));
+    first
+        .then(rest.repeated().collect::<String>())
+        .map(|(first, rest)| format!("{first}{rest}"))
+}
+
+// -1,234.56, the decimal mark is always a period and digits are grouped by commas
+pub fn number<'a>(
+) -> impl Parser<'a, &'a str, (Decimal, Style), extra::Full<Rich<'a, char>, State, ()>> {
+    one_of("-+")
+        .or_not()
+        .then(
+            any()
+                .filter(|c: &char| c.is_ascii_digit() || *c == ',' || *c == '.')
+                .repeated()
+                .at_least(1)
+                .collect::<String>(),
+        )
+        .try_map(|(sign, digits), span| {
+            let mut number = Decimal::from_str(&digits.replace(',', ""))
+                .map_err(|error| Rich::custom(span, error))?;
+            if sign == Some('-') {
+                number.set_sign_negative(true);
+            }
+            let style = Style {
+                decimal_mark: digits.contains('.').then_some('.'),
+                digit_groups: digits.contains(',').then(|| (',', vec![3])),
+            };
+            Ok((number, style))
+        })
+}
+
+// 100.00 USD, optionally with a balance tolerance: 100.00 ~ 0.01 USD
+pub fn amount<'a>() -> impl Parser<'a, &'a str, Amount, extra::Full<Rich<'a, char>, State, ()>> {
+    let tolerance = just("~")
+        .then(whitespace().repeated())
+        .then(number())
+        .then(whitespace().repeated());
+    number()
+        .then_ignore(whitespace().repeated())
+        .then_ignore(tolerance.or_not())
+        .then(commodity())
+        .map(|((quantity, style), commodity)| Amount {
+            quantity,
+            commodity,
+            price: None,
+            style,
+        })
+}
+
+// "string with \"escaped\" quotes"
+pub fn string<'a>() -> impl Parser<'a, &'a str, String, extra::Full<Rich<'a, char>, State, ()>> {
+    just("\\")
+        .ignore_then(any())
+        .or(any().and_is(just("\"").not()))
+        .repeated()
+        .collect::<String>()
+        .delimited_by(just("\""), just("\""))
+}
+
+/// Cost of a posting: `{502.12 USD}`, `{{1000 USD}}` or `{502.12 USD, 2014-05-05, "lot"}`.
+/// Only the cost amount is kept, the lot date and label are ignored.
+pub fn cost<'a>(
+) -> impl Parser<'a, &'a str, Option<AmountPrice>, extra::Full<Rich<'a, char>, State, ()>> {
+    let elements = || {
+        choice((
+            amount().map(Some),
+            date().map(|_| None),
+            string().map(|_| None),
+            just("*").map(|_| None),
+        ))
+        .separated_by(just(",").padded_by(whitespace().repeated()))
+        .collect::<Vec<_>>()
+        .padded_by(whitespace().repeated())
+        .map(|elements| elements.into_iter().flatten().next())
+    };
+    elements()
+        .delimited_by(just("{{"), just("}}"))
+        .map(|amount| amount.map(AmountPrice::Total))
+        .or(elements()
+            .delimited_by(just("{"), just("}"))
+            .map(|amount| amount.map(AmountPrice::Unit)))
+}
+
+// @ 1.25 USD or @@ 125 USD
+pub fn price<'a>() -> impl Parser<'a, &'a str, AmountPrice, extra::Full<Rich<'a, char>, State, ()>>
+{
+    just("@@")
+        .ignore_then(whitespace().repeated())
+        .ignore_then(amount())
+        .map(AmountPrice::Total)
+        .or(just("@")
+            .ignore_then(whitespace().repeated())
+            .ignore_then(amount())
+            .map(AmountPrice::Unit))
+}
+
+/// Metadata `key: value`, kept as a tag. Strings are unquoted, other values are kept as written.
+pub fn metadata<'a>(
+) -> impl Parser<'a, &'a str, (String, String), extra::Full<Rich<'a, char>, State, ()>> {
+    let value = string().or(any()
+        .and_is(text::newline().not())
+        .and_is(just(";").not())
+        .repeated()
+        .to_slice()
+        .map(|value: &str| value.trim_end().to_string()));
+    any()
+        .filter(char::is_ascii_lowercase)
+        .then(
+            any()
+                .filter(|c: &char| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+                .repeated(),
+        )
+        .to_slice()
+        .map(ToString::to_string)
+        .then_ignore(just(":"))
+        .then_ignore(whitespace().repeated())
+        .then(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn account_name() {
+        let result = account()
+            .then_ignore(end())
+            .parse("Liabilities:US:Chase-Card")
+            .into_result();
+        assert_eq!(
+            result,
+            Ok(AccountName::from_parts(&[
+                String::from("Liabilities"),
+                String::from("US"),
+                String::from("Chase-Card"),
+            ]))
+        );
+    }
+
+    #[test]
+    fn amount_with_grouping() {
+        let result = amount()
+            .then_ignore(end())
+            .parse("-1,234.5 USD")
+            .into_result();
+        assert_eq!(
+            result,
+            Ok(Amount {
+                quantity: Decimal::new(-12345, 1),
+                commodity: String::from("USD"),
+                price: None,
+                ..Amount::default()
+            })
+        );
+    }
+
+    #[test]
+    fn cost_with_lot() {
+        let result = cost()
+            .then_ignore(end())
+            .parse("{502.12 USD, 2014-05-05, \"lot\"}")
+            .into_result();
+        assert_eq!(
+            result,
+            Ok(Some(AmountPrice::Unit(Amount {
+                quantity: Decimal::new(50212, 2),
+                commodity: String::from("USD"),
+                price: None,
+                ..Amount::default()
+            })))
+        );
+    }
+
+    #[test]
+    fn empty_cost() {
+        let result = cost().then_ignore(end()).parse("{}").into_result();
+        assert_eq!(result, Ok(None));
+    }
+
+    #[test]
+    fn escaped_string() {
+        let result = string()
+            .then_ignore(end())
+            .parse("\"say \\\"hi\\\"\"")
+            .into_result();
+        assert_eq!(result, Ok(String::from("say \"hi\"")));
+    }
+
+    #[test]
+    fn metadata_values() {
+        let result = metadata()
+            .then_ignore(end())
+            .parse("invoice: \"1234\"")
+            .into_result();
+        assert_eq!(result, Ok((String::from("invoice"), String::from("1234"))));
+        let result = metadata()
+            .then_ignore(end())
+            .parse("rate-2:  1.25 USD")
+            .into_result();
+        assert_eq!(
+            result,
+            Ok((String::from("rate-2"), String::from("1.25 USD")))
+        );
+    }
+}
@@ -0,0 +1,220 @@
+use chumsky::prelude::*;
+
+use crate::beancount::component::{account, amount, cost, metadata, price, string};
+use crate::component::amount::Amount;
+use crate::component::date::simple::date;
+use crate::component::status::Status;
+use crate::component::whitespace::whitespace;
+use crate::directive::{Posting, Transaction};
+use crate::state::State;
+use crate::utils::end_of_line;
+
+// * is cleared, ! is pending, other flags mark generated transactions and have no status
+fn flag<'a>() -> impl Parser<'a, &'a str, Option<Status>, extra::Full<Rich<'a, char>, State, ()>> {
+    choice((
+        just("txn").to(Some(Status::Cleared)),
+        just("*").to(Some(Status::Cleared)),
+        just("!").to(Some(Status::Pending)),
+        one_of("&#?%PSTCURM").to(None),
+    ))
+}
+
+// #tag or ^link, tags are kept as tags without a value
+fn tag_or_link<'a>(
+) -> impl Parser<'a, &'a str, Option<(String, String)>, extra::Full<Rich<'a, char>, State, ()>> {
+    one_of("#^")
+        .then(
+            any()
+                .filter(|c: &char| c.is_alphanumeric() || "-_/.".contains(*c))
+                .repeated()
+                .at_least(1)
+                .collect::<String>(),
+        )
+        .map(|(kind, name)| (kind == '#').then(|| (name, String::new())))
+}
+
+// a posting or a metadata line
+enum Line {
+    Posting(Posting),
+    Metadata((String, String)),
+}
+
+fn posting<'a>() -> impl Parser<'a, &'a str, Posting, extra::Full<Rich<'a, char>, State, ()>> {
+    let posting_amount = whitespace()
+        .repeated()
+        .at_least(1)
+        .ignore_then(amount())
+        .then(whitespace().repeated().ignore_then(cost()).or_not())
+        .then(whitespace().repeated().ignore_then(price()).or_not());
+    whitespace()
+        .repeated()
+        .at_least(1)
+        .ignore_then(
+            flag()
+                .then_ignore(whitespace().repeated().at_least(1))
+                .or_not(),
+        )
+        .then(account())
+        .then(posting_amount.or_not())
+        .then_ignore(end_of_line())
+        .map_with(|((status, account_name), amount), e| Posting {
+            status: status.flatten(),
+            account_name,
+            is_virtual: false,
+            is_balanced_virtual: false,
+            is_generated: false,
+            comment: None,
+            tags: Vec::new(),
+            is_amount_specified: amount.is_some(),
+            // postings held at cost are balanced by their cost, the price only records the
+            // market price
+            amount: amount
+                .map(|((amount, cost), price)| Amount {
+                    price: cost.flatten().or(price).map(Box::new),
+                    ..amount
+                })
+                .into_iter()
+                .collect(),
+            assertion: None,
+            position: e.span().into_range(),
+        })
+}
+
+pub fn transaction<'a>(
+) -> impl Parser<'a, &'a str, Transaction, extra::Full<Rich<'a, char>, State, ()>> {
+    let strings = whitespace()
+        .repeated()
+        .at_least(1)
+        .ignore_then(string())
+        .repeated()
+        .at_most(2)
+        .collect::<Vec<_>>();
+    let header = date()
+        .then_ignore(whitespace().repeated().at_least(1))
+        .then(flag())
+        .then(strings)
+        .then(
+            whitespace()
+                .repeated()
+                .at_least(1)
+                .ignore_then(tag_or_link())
+                .repeated()
+                .collect::<Vec<_>>(),
+        )
+        .then_ignore(end_of_line());
+    let line = posting().map(Line::Posting).or(whitespace()
+        .repeated()
+        .at_least(1)
+        .ignore_then(metadata())
+        .then_ignore(end_of_line())
+        .map(Line::Metadata));
+    header
+        .then(
+            text::newline()
+                .ignore_then(line)
+                .repeated()
+                .collect::<Vec<_>>(),
+        )
+        .map_with(|((((date, status), strings), tags), lines), e| {
+            // a single string is the narration, two strings are the payee and the narration
+            let mut strings = strings.into_iter();
+            let (payee, note) = match (strings.next(), strings.next()) {
+                (Some(payee), Some(narration)) => (payee, Some(narration)),
+                (Some(narration), None) => (narration, None),
+                _ => (String::new(), None),
+            };
+            // metadata belongs to the posting before it, or to the transaction
+            let mut tags = tags.into_iter().flatten().collect::<Vec<_>>();
+            let mut postings = Vec::<Posting>::new();
+            for line in lines {
+                match (line, postings.last_mut()) {
+                    (Line::Posting(posting), _) => postings.push(posting),
+                    (Line::Metadata(tag), Some(posting)) => posting.tags.push(tag),
+                    (Line::Metadata(tag), None) => tags.push(tag),
+                }
+            }
+            Transaction {
+                date,
+                status,
+                code: None,
+                payee,
+                note,
+                postings,
+                position: e.span().into_range(),
+                is_generated: false,
+                comment: None,
+                tags,
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal::Decimal;
+
+    use super::*;
+    use crate::component::account_name::AccountName;
+    use crate::component::price::AmountPrice;
+
+    #[test]
+    fn full() {
+        let result = transaction()
+            .then_ignore(end())
+            .parse(
+                "2014-05-05 * \"Cafe Mogador\" \"Lamb tagine with wine\" #trip ^receipt
+  invoice: \"1234\"
+  ! Liabilities:CreditCard:CapitalOne         -37.45 USD ; card
+  Assets:Stock  10 HOOL {502.12 USD} @ 510.00 USD
+    lot: \"first\"
+  Expenses:Restaurant",
+            )
+            .into_result()
+            .unwrap();
+        assert_eq!(result.status, Some(Status::Cleared));
+        assert_eq!(result.payee, "Cafe Mogador");
+        assert_eq!(result.note, Some(String::from("Lamb tagine with wine")));
+        assert_eq!(result.postings.len(), 3);
+        assert_eq!(
+            result.tags,
+            vec![
+                (String::from("trip"), String::new()),
+                (String::from("invoice"), String::from("1234")),
+            ]
+        );
+        assert_eq!(
+            result.postings[1].tags,
+            vec![(String::from("lot"), String::from("first"))]
+        );
+        assert_eq!(result.postings[0].status, Some(Status::Pending));
+        assert_eq!(
+            result.postings[1].amount,
+            vec![Amount {
+                quantity: Decimal::new(10, 0),
+                commodity: String::from("HOOL"),
+                price: Some(Box::new(AmountPrice::Unit(Amount {
+                    quantity: Decimal::new(50212, 2),
+                    commodity: String::from("USD"),
+                    price: None,
+                    ..Amount::default()
+                }))),
+                ..Amount::default()
+            }]
+        );
+        assert_eq!(
+            result.postings[2].account_name,
+            AccountName::from_parts(&[String::from("Expenses"), String::from("Restaurant")])
+        );
+        assert!(!result.postings[2].is_amount_specified);
+    }
+
+    #[test]
+    fn narration_only() {
+        let result = transaction()
+            .then_ignore(end())
+            .parse("2014-05-05 txn \"Transfer\"\n  Assets:A  1 USD\n  Assets:B")
+            .into_result()
+            .unwrap();
+        assert_eq!(result.payee, "Transfer");
+        assert_eq!(result.note, None);
+    }
+}
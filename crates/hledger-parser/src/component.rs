@@ -4,6 +4,7 @@ pub mod comment;
 pub mod commodity;
 pub mod date;
 pub mod interval;
+pub mod metadata;
 pub mod period;
 pub mod price;
 pub mod quantity;
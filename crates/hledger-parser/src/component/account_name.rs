@@ -4,13 +4,28 @@ use chumsky::prelude::*;
 
 use crate::state::State;
 
+/// Account name, made of its `:` separated parts.
 #[derive(Debug, Clone, Hash, PartialEq)]
 pub struct AccountName(Vec<String>);
 
 impl AccountName {
+    /// Account name made of the given parts, outermost first.
+    #[must_use]
     pub fn from_parts(parts: &[String]) -> Self {
         Self(parts.to_vec())
     }
+
+    /// Returns the account name under the parent accounts, outermost first.
+    #[must_use]
+    pub fn under(self, parents: &[AccountName]) -> Self {
+        Self(
+            parents
+                .iter()
+                .flat_map(|parent| parent.0.iter().cloned())
+                .chain(self.0)
+                .collect(),
+        )
+    }
 }
 
 impl std::fmt::Display for AccountName {
@@ -26,10 +41,13 @@ pub fn account_name<'a>(
         .and_is(just(":").not()) // forbidden, because it separates account parts
         .and_is(just("  ").not()) // forbidden, because it separates inline account comment
         .and_is(just(")").not()) // forbidden, because it indicates virtual posting
+        .and_is(just("]").not()) // forbidden, because it indicates balanced virtual posting
         .map(|c| format!("{c}"));
 
-    // do not allow closing parenthesis in the end of account name, but allow them in the middle
-    let paren_with_following = just(')').then(regular_char).map(|(p, c)| format!("{p}{c}"));
+    // do not allow closing brackets in the end of account name, but allow them in the middle
+    let paren_with_following = one_of(")]")
+        .then(regular_char)
+        .map(|(p, c)| format!("{p}{c}"));
     let valid_segment = paren_with_following.or(regular_char);
 
     let part = valid_segment.repeated().at_least(1).collect::<Vec<_>>();
@@ -76,4 +94,19 @@ mod tests {
             ]))
         );
     }
+
+    #[test]
+    fn ok_square_brackets() {
+        let result = account_name()
+            .then_ignore(end())
+            .parse("assets:[cash] box")
+            .into_result();
+        assert_eq!(
+            result,
+            Ok(AccountName::from_parts(&[
+                String::from("assets"),
+                String::from("[cash] box"),
+            ]))
+        );
+    }
 }
@@ -2,20 +2,40 @@ use chumsky::prelude::*;
 use rust_decimal::Decimal;
 
 use crate::component::commodity::commodity;
-use crate::component::quantity::quantity;
+use crate::component::quantity::{styled_quantity, Style};
 use crate::component::whitespace::whitespace;
 use crate::state::State;
 use crate::AmountPrice;
 
-#[derive(Debug, Default, Clone, Hash, PartialEq)]
+#[derive(Debug, Default, Clone)]
 pub struct Amount {
     pub quantity: Decimal,
     pub commodity: String,
     pub price: Option<Box<AmountPrice>>,
+    /// Style the quantity was written in. It only affects display, so amounts that differ only
+    /// in style are equal.
+    pub style: Style,
+}
+
+impl PartialEq for Amount {
+    fn eq(&self, other: &Self) -> bool {
+        self.quantity == other.quantity
+            && self.commodity == other.commodity
+            && self.price == other.price
+    }
+}
+
+impl std::hash::Hash for Amount {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.quantity.hash(state);
+        self.commodity.hash(state);
+        self.price.hash(state);
+    }
 }
 
 impl Amount {
-    #[allow(clippy::missing_panics_doc)]
+    /// Returns the cost of the amount in the commodity of its price, or `None` when it has no
+    /// price or when the cost is too large to compute.
     #[must_use]
     pub fn cost(&self) -> Option<Amount> {
         match self.price.as_ref() {
@@ -30,9 +50,10 @@ impl Amount {
                     Some(price)
                 }
                 crate::AmountPrice::Unit(price) => Some(Amount {
-                    quantity: price.quantity.checked_mul(self.quantity).expect("overflow"),
+                    quantity: price.quantity.checked_mul(self.quantity)?,
                     commodity: price.commodity.clone(),
                     price: None,
+                    style: price.style.clone(),
                 }),
             },
         }
@@ -42,10 +63,10 @@ impl Amount {
 pub fn amount<'a>() -> impl Parser<'a, &'a str, Amount, extra::Full<Rich<'a, char>, State, ()>> {
     let sign_quantity_commodity = one_of("-+")
         .then_ignore(whitespace().repeated())
-        .then(quantity())
+        .then(styled_quantity())
         .then_ignore(whitespace().repeated())
         .then(commodity())
-        .map(|((sign, mut quantity), commodity)| {
+        .map(|((sign, (mut quantity, style)), commodity)| {
             if sign == '-' {
                 quantity.set_sign_negative(true);
             }
@@ -53,14 +74,15 @@ pub fn amount<'a>() -> impl Parser<'a, &'a str, Amount, extra::Full<Rich<'a, cha
                 quantity,
                 commodity,
                 price: None,
+                style,
             }
         });
-    let quantity_sign_commodity = quantity()
+    let quantity_sign_commodity = styled_quantity()
         .then_ignore(whitespace().repeated())
         .then(one_of("-+"))
         .then_ignore(whitespace().repeated())
         .then(commodity())
-        .map(|((mut quantity, sign), commodity)| {
+        .map(|(((mut quantity, style), sign), commodity)| {
             if sign == '-' {
                 quantity.set_sign_negative(true);
             }
@@ -68,14 +90,15 @@ pub fn amount<'a>() -> impl Parser<'a, &'a str, Amount, extra::Full<Rich<'a, cha
                 quantity,
                 commodity,
                 price: None,
+                style,
             }
         });
     let sign_commodity_quantity = one_of("-+")
         .then_ignore(whitespace().repeated())
         .then(commodity())
         .then_ignore(whitespace().repeated())
-        .then(quantity())
-        .map(|((sign, commodity), mut quantity)| {
+        .then(styled_quantity())
+        .map(|((sign, commodity), (mut quantity, style))| {
             if sign == '-' {
                 quantity.set_sign_negative(true);
             }
@@ -83,14 +106,15 @@ pub fn amount<'a>() -> impl Parser<'a, &'a str, Amount, extra::Full<Rich<'a, cha
                 quantity,
                 commodity,
                 price: None,
+                style,
             }
         });
     let commodity_sign_quantity = commodity()
         .then_ignore(whitespace().repeated())
         .then(one_of("-+"))
         .then_ignore(whitespace().repeated())
-        .then(quantity())
-        .map(|((commodity, sign), mut quantity)| {
+        .then(styled_quantity())
+        .map(|((commodity, sign), (mut quantity, style))| {
             if sign == '-' {
                 quantity.set_sign_negative(true);
             }
@@ -98,28 +122,32 @@ pub fn amount<'a>() -> impl Parser<'a, &'a str, Amount, extra::Full<Rich<'a, cha
                 quantity,
                 commodity,
                 price: None,
+                style,
             }
         });
-    let quantity_commodity = quantity()
+    let quantity_commodity = styled_quantity()
         .then_ignore(whitespace().repeated())
         .then(commodity())
-        .map(|(quantity, commodity)| Amount {
+        .map(|((quantity, style), commodity)| Amount {
             quantity,
             commodity,
             price: None,
+            style,
         });
     let commodity_quantity = commodity()
         .then_ignore(whitespace().repeated())
-        .then(quantity())
-        .map(|(commodity, quantity)| Amount {
+        .then(styled_quantity())
+        .map(|(commodity, (quantity, style))| Amount {
             quantity,
             commodity,
             price: None,
+            style,
         });
-    let just_quantity = quantity().map(|quantity| Amount {
+    let just_quantity = styled_quantity().map(|(quantity, style)| Amount {
         quantity,
         commodity: String::new(),
         price: None,
+        style,
     });
     choice((
         sign_quantity_commodity.boxed(),
@@ -157,6 +185,7 @@ mod tests {
                     quantity: Decimal::new(1, 0),
                     commodity: String::from("$"),
                     price: None,
+                    ..Amount::default()
                 },
             ),
             (
@@ -165,6 +194,7 @@ mod tests {
                     quantity: Decimal::new(4000, 0),
                     commodity: String::from("AAPL"),
                     price: None,
+                    ..Amount::default()
                 },
             ),
             (
@@ -173,6 +203,7 @@ mod tests {
                     quantity: Decimal::new(3, 0),
                     commodity: String::from("green apples"),
                     price: None,
+                    ..Amount::default()
                 },
             ),
         ] {
@@ -190,6 +221,7 @@ mod tests {
                     quantity: Decimal::new(-1, 0),
                     commodity: String::from("$"),
                     price: None,
+                    ..Amount::default()
                 },
             ),
             (
@@ -198,6 +230,7 @@ mod tests {
                     quantity: Decimal::new(-1, 0),
                     commodity: String::from("$"),
                     price: None,
+                    ..Amount::default()
                 },
             ),
             (
@@ -206,6 +239,7 @@ mod tests {
                     quantity: Decimal::new(1, 0),
                     commodity: String::from("$"),
                     price: None,
+                    ..Amount::default()
                 },
             ),
             (
@@ -214,6 +248,7 @@ mod tests {
                     quantity: Decimal::new(-1, 0),
                     commodity: String::from("$"),
                     price: None,
+                    ..Amount::default()
                 },
             ),
             (
@@ -222,6 +257,7 @@ mod tests {
                     quantity: Decimal::new(-1, 0),
                     commodity: String::from("USD"),
                     price: None,
+                    ..Amount::default()
                 },
             ),
         ] {
@@ -1,11 +1,24 @@
 use chumsky::prelude::*;
 
+use crate::component::metadata::tags;
 use crate::component::whitespace::whitespace;
 use crate::state::State;
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Comment(String);
 
+impl Comment {
+    /// Text of the comment, its lines without their comment marker.
+    pub fn text(&self) -> &str {
+        &self.0
+    }
+
+    /// `name: value` tags found in the comment, in order.
+    pub fn tags(&self) -> Vec<(String, String)> {
+        self.0.lines().flat_map(tags).collect()
+    }
+}
+
 pub fn line<'a>() -> impl Parser<'a, &'a str, Comment, extra::Full<Rich<'a, char>, State, ()>> {
     just("#")
         .ignore_then(
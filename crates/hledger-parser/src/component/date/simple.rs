@@ -86,7 +86,13 @@ mod tests {
         ] {
             let result = date()
                 .then_ignore(end())
-                .parse_with_state(input, &mut State { year: 2011 })
+                .parse_with_state(
+                    input,
+                    &mut State {
+                        year: 2011,
+                        ..State::default()
+                    },
+                )
                 .into_result();
             assert_eq!(result, Ok(expected), "{input}");
         }
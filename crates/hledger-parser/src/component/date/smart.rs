@@ -1,5 +1,3 @@
-use std::time::SystemTime;
-
 use chrono::Datelike;
 use chumsky::prelude::*;
 
@@ -53,23 +51,25 @@ fn periods_ago<'a>(
         .then(period())
         .then_ignore(whitespace().repeated().at_least(1))
         .then_ignore(just("ago"))
-        .try_map(|(length, period), span| {
+        .try_map_with(|(length, period), e| {
+            let state: &mut State = e.state();
+            let today = state.today;
             match period {
                 Period::Day => {
-                    today().checked_sub_days(chrono::Days::new(length.parse::<u64>().unwrap()))
+                    today.checked_sub_days(chrono::Days::new(length.parse::<u64>().unwrap()))
                 }
                 Period::Week => {
-                    today().checked_sub_days(chrono::Days::new(length.parse::<u64>().unwrap() * 7))
+                    today.checked_sub_days(chrono::Days::new(length.parse::<u64>().unwrap() * 7))
                 }
                 Period::Month => {
-                    today().checked_sub_months(chrono::Months::new(length.parse::<u32>().unwrap()))
+                    today.checked_sub_months(chrono::Months::new(length.parse::<u32>().unwrap()))
                 }
-                Period::Quarter => today()
+                Period::Quarter => today
                     .checked_sub_months(chrono::Months::new(length.parse::<u32>().unwrap() * 3)),
-                Period::Year => today()
+                Period::Year => today
                     .checked_sub_months(chrono::Months::new(length.parse::<u32>().unwrap() * 12)),
             }
-            .ok_or(Rich::custom(span, "not a valid date"))
+            .ok_or(Rich::custom(e.span(), "not a valid date"))
         })
 }
 
@@ -81,23 +81,25 @@ fn periods_ahead<'a>(
         .then(period())
         .then_ignore(whitespace().repeated().at_least(1))
         .then_ignore(just("ahead"))
-        .try_map(|(length, period), span| {
+        .try_map_with(|(length, period), e| {
+            let state: &mut State = e.state();
+            let today = state.today;
             match period {
                 Period::Day => {
-                    today().checked_add_days(chrono::Days::new(length.parse::<u64>().unwrap()))
+                    today.checked_add_days(chrono::Days::new(length.parse::<u64>().unwrap()))
                 }
                 Period::Week => {
-                    today().checked_add_days(chrono::Days::new(length.parse::<u64>().unwrap() * 7))
+                    today.checked_add_days(chrono::Days::new(length.parse::<u64>().unwrap() * 7))
                 }
                 Period::Month => {
-                    today().checked_add_months(chrono::Months::new(length.parse::<u32>().unwrap()))
+                    today.checked_add_months(chrono::Months::new(length.parse::<u32>().unwrap()))
                 }
-                Period::Quarter => today()
+                Period::Quarter => today
                     .checked_add_months(chrono::Months::new(length.parse::<u32>().unwrap() * 3)),
-                Period::Year => today()
+                Period::Year => today
                     .checked_add_months(chrono::Months::new(length.parse::<u32>().unwrap() * 12)),
             }
-            .ok_or(Rich::custom(span, "not a valid date"))
+            .ok_or(Rich::custom(e.span(), "not a valid date"))
         })
 }
 
@@ -109,23 +111,25 @@ fn n_periods<'a>(
         .ignore_then(text::int(10))
         .then_ignore(whitespace().repeated().at_least(1))
         .then(period())
-        .try_map(|(length, period), span| {
+        .try_map_with(|(length, period), e| {
+            let state: &mut State = e.state();
+            let today = state.today;
             match period {
                 Period::Day => {
-                    today().checked_add_days(chrono::Days::new(length.parse::<u64>().unwrap()))
+                    today.checked_add_days(chrono::Days::new(length.parse::<u64>().unwrap()))
                 }
                 Period::Week => {
-                    today().checked_add_days(chrono::Days::new(length.parse::<u64>().unwrap() * 7))
+                    today.checked_add_days(chrono::Days::new(length.parse::<u64>().unwrap() * 7))
                 }
                 Period::Month => {
-                    today().checked_add_months(chrono::Months::new(length.parse::<u32>().unwrap()))
+                    today.checked_add_months(chrono::Months::new(length.parse::<u32>().unwrap()))
                 }
-                Period::Quarter => today()
+                Period::Quarter => today
                     .checked_add_months(chrono::Months::new(length.parse::<u32>().unwrap() * 3)),
-                Period::Year => today()
+                Period::Year => today
                     .checked_add_months(chrono::Months::new(length.parse::<u32>().unwrap() * 12)),
             }
-            .ok_or(Rich::custom(span, "not a valid date"))
+            .ok_or(Rich::custom(e.span(), "not a valid date"))
         })
 }
 
@@ -135,45 +139,47 @@ fn rel_word_period<'a>(
     choice([just("last").to(-1), just("this").to(0), just("next").to(1)])
         .then_ignore(whitespace().repeated().at_least(1))
         .then(period())
-        .try_map(|(rel, period), span| {
+        .try_map_with(|(rel, period), e| {
+            let state: &mut State = e.state();
+            let today = state.today;
             match period {
                 Period::Day => {
                     if rel >= 0 {
-                        today().checked_add_days(chrono::Days::new(1))
+                        today.checked_add_days(chrono::Days::new(1))
                     } else {
-                        today().checked_sub_days(chrono::Days::new(1))
+                        today.checked_sub_days(chrono::Days::new(1))
                     }
                 }
                 Period::Week => {
                     if rel >= 0 {
-                        today().checked_add_days(chrono::Days::new(7))
+                        today.checked_add_days(chrono::Days::new(7))
                     } else {
-                        today().checked_sub_days(chrono::Days::new(7))
+                        today.checked_sub_days(chrono::Days::new(7))
                     }
                 }
                 Period::Month => {
                     if rel >= 0 {
-                        today().checked_add_months(chrono::Months::new(1))
+                        today.checked_add_months(chrono::Months::new(1))
                     } else {
-                        today().checked_sub_months(chrono::Months::new(1))
+                        today.checked_sub_months(chrono::Months::new(1))
                     }
                 }
                 Period::Quarter => {
                     if rel >= 0 {
-                        today().checked_add_months(chrono::Months::new(3))
+                        today.checked_add_months(chrono::Months::new(3))
                     } else {
-                        today().checked_sub_months(chrono::Months::new(3))
+                        today.checked_sub_months(chrono::Months::new(3))
                     }
                 }
                 Period::Year => {
                     if rel >= 0 {
-                        today().checked_add_months(chrono::Months::new(12))
+                        today.checked_add_months(chrono::Months::new(12))
                     } else {
-                        today().checked_sub_months(chrono::Months::new(12))
+                        today.checked_sub_months(chrono::Months::new(12))
                     }
                 }
             }
-            .ok_or(Rich::custom(span, "not a valid date"))
+            .ok_or(Rich::custom(e.span(), "not a valid date"))
         })
 }
 
@@ -181,10 +187,17 @@ fn rel_word_period<'a>(
 fn words<'a>() -> impl Parser<'a, &'a str, chrono::NaiveDate, extra::Full<Rich<'a, char>, State, ()>>
 {
     choice([
-        just("yesterday").to(today().checked_sub_days(chrono::Days::new(1)).unwrap()),
-        just("today").to(today()),
-        just("tomorrow").to(today().checked_add_days(chrono::Days::new(1)).unwrap()),
+        just("yesterday").to(-1),
+        just("today").to(0),
+        just("tomorrow").to(1),
     ])
+    .try_map_with(|days, e| {
+        let state: &mut State = e.state();
+        state
+            .today
+            .checked_add_signed(chrono::TimeDelta::days(days))
+            .ok_or(Rich::custom(e.span(), "not a valid date"))
+    })
 }
 
 // 2024/10/1: exact date
@@ -344,54 +357,52 @@ fn just_day<'a>(
         .collect::<String>()
         .from_str::<u32>()
         .unwrapped()
-        .try_map(|day, span| {
-            if let Some(date) = today().with_day(day) {
+        .try_map_with(|day, e| {
+            let state: &mut State = e.state();
+            if let Some(date) = state.today.with_day(day) {
                 Ok(date)
             } else {
                 Err(Rich::custom(
-                    span,
+                    e.span(),
                     format!("{day} day does not exist in the current month"),
                 ))
             }
         })
 }
 
-// returns today's date
-fn today() -> chrono::NaiveDate {
-    let current_time = SystemTime::now();
-    let datetime: chrono::DateTime<chrono::Local> = current_time.into();
-    datetime.date_naive()
-}
-
 // oct or october: October 1st in current year
 fn month_name<'a>(
 ) -> impl Parser<'a, &'a str, chrono::NaiveDate, extra::Full<Rich<'a, char>, State, ()>> {
-    let start_of_month = |m: u32| today().with_day(1).unwrap().with_month(m).unwrap();
     choice([
-        just("january").to(start_of_month(1)),
-        just("jan").to(start_of_month(1)),
-        just("february").to(start_of_month(2)),
-        just("feb").to(start_of_month(2)),
-        just("march").to(start_of_month(3)),
-        just("mar").to(start_of_month(3)),
-        just("april").to(start_of_month(4)),
-        just("apr").to(start_of_month(4)),
-        just("may").to(start_of_month(5)),
-        just("june").to(start_of_month(6)),
-        just("jun").to(start_of_month(6)),
-        just("july").to(start_of_month(7)),
-        just("jul").to(start_of_month(7)),
-        just("august").to(start_of_month(8)),
-        just("aug").to(start_of_month(8)),
-        just("september").to(start_of_month(9)),
-        just("sep").to(start_of_month(9)),
-        just("october").to(start_of_month(10)),
-        just("oct").to(start_of_month(10)),
-        just("november").to(start_of_month(11)),
-        just("nov").to(start_of_month(11)),
-        just("december").to(start_of_month(12)),
-        just("dec").to(start_of_month(12)),
+        just("january").to(1),
+        just("jan").to(1),
+        just("february").to(2),
+        just("feb").to(2),
+        just("march").to(3),
+        just("mar").to(3),
+        just("april").to(4),
+        just("apr").to(4),
+        just("may").to(5),
+        just("june").to(6),
+        just("jun").to(6),
+        just("july").to(7),
+        just("jul").to(7),
+        just("august").to(8),
+        just("aug").to(8),
+        just("september").to(9),
+        just("sep").to(9),
+        just("october").to(10),
+        just("oct").to(10),
+        just("november").to(11),
+        just("nov").to(11),
+        just("december").to(12),
+        just("dec").to(12),
     ])
+    .map_with(|month, e| {
+        let state: &mut State = e.state();
+        chrono::NaiveDate::from_ymd_opt(state.today.year(), month, 1)
+            .expect("first day of month is valid")
+    })
 }
 
 // 8 digit YYYYMMDD with valid year month and day
@@ -425,9 +436,24 @@ mod tests {
 
     use super::*;
 
+    fn state() -> State {
+        State {
+            year: 2024,
+            today: chrono::NaiveDate::from_ymd_opt(2024, 10, 15).unwrap(),
+            ..State::default()
+        }
+    }
+
+    fn today() -> chrono::NaiveDate {
+        state().today
+    }
+
     #[test]
     fn start_of_month_numeric() {
-        let result = date().then_ignore(end()).parse("2024-03").into_result();
+        let result = date()
+            .then_ignore(end())
+            .parse_with_state("2024-03", &mut state())
+            .into_result();
         assert_eq!(
             result,
             Ok(chrono::NaiveDate::from_ymd_opt(2024, 3, 1).unwrap()),
@@ -436,13 +462,19 @@ mod tests {
 
     #[test]
     fn start_of_month_numeric_err() {
-        let result = date().then_ignore(end()).parse("2024-31").into_result();
+        let result = date()
+            .then_ignore(end())
+            .parse_with_state("2024-31", &mut state())
+            .into_result();
         assert!(result.is_err());
     }
 
     #[test]
     fn four_digits() {
-        let result = date().then_ignore(end()).parse("2024").into_result();
+        let result = date()
+            .then_ignore(end())
+            .parse_with_state("2024", &mut state())
+            .into_result();
         assert_eq!(
             result,
             Ok(chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()),
@@ -451,7 +483,10 @@ mod tests {
 
     #[test]
     fn eight_digits() {
-        let result = date().then_ignore(end()).parse("20240112").into_result();
+        let result = date()
+            .then_ignore(end())
+            .parse_with_state("20240112", &mut state())
+            .into_result();
         assert_eq!(
             result,
             Ok(chrono::NaiveDate::from_ymd_opt(2024, 1, 12).unwrap()),
@@ -460,13 +495,19 @@ mod tests {
 
     #[test]
     fn eightsix_digits_err() {
-        let result = date().then_ignore(end()).parse("20240132").into_result();
+        let result = date()
+            .then_ignore(end())
+            .parse_with_state("20240132", &mut state())
+            .into_result();
         assert!(result.is_err());
     }
 
     #[test]
     fn six_digits() {
-        let result = date().then_ignore(end()).parse("202401").into_result();
+        let result = date()
+            .then_ignore(end())
+            .parse_with_state("202401", &mut state())
+            .into_result();
         assert_eq!(
             result,
             Ok(chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()),
@@ -475,13 +516,19 @@ mod tests {
 
     #[test]
     fn six_digits_err() {
-        let result = date().then_ignore(end()).parse("202431").into_result();
+        let result = date()
+            .then_ignore(end())
+            .parse_with_state("202431", &mut state())
+            .into_result();
         assert!(result.is_err());
     }
 
     #[test]
     fn year_month_day() {
-        let result = date().then_ignore(end()).parse("2018.10.1").into_result();
+        let result = date()
+            .then_ignore(end())
+            .parse_with_state("2018.10.1", &mut state())
+            .into_result();
         assert_eq!(
             result,
             Ok(chrono::NaiveDate::from_ymd_opt(2018, 10, 1).unwrap())
@@ -490,13 +537,19 @@ mod tests {
 
     #[test]
     fn year_month_day_err() {
-        let result = date().then_ignore(end()).parse("2023/13/1").into_result();
+        let result = date()
+            .then_ignore(end())
+            .parse_with_state("2023/13/1", &mut state())
+            .into_result();
         assert!(result.is_err());
     }
 
     #[test]
     fn month_day() {
-        let result = date().then_ignore(end()).parse("10/1").into_result();
+        let result = date()
+            .then_ignore(end())
+            .parse_with_state("10/1", &mut state())
+            .into_result();
         assert_eq!(
             result,
             Ok(today().with_month(10).unwrap().with_day(1).unwrap())
@@ -505,13 +558,19 @@ mod tests {
 
     #[test]
     fn month_day_err() {
-        let result = date().then_ignore(end()).parse("13/1").into_result();
+        let result = date()
+            .then_ignore(end())
+            .parse_with_state("13/1", &mut state())
+            .into_result();
         assert!(result.is_err());
     }
 
     #[test]
     fn month_name() {
-        let result = date().then_ignore(end()).parse("october").into_result();
+        let result = date()
+            .then_ignore(end())
+            .parse_with_state("october", &mut state())
+            .into_result();
         assert_eq!(
             result,
             Ok(today().with_month(10).unwrap().with_day(1).unwrap())
@@ -520,7 +579,10 @@ mod tests {
 
     #[test]
     fn in_three_days() {
-        let result = date().then_ignore(end()).parse("in  3 days").into_result();
+        let result = date()
+            .then_ignore(end())
+            .parse_with_state("in  3 days", &mut state())
+            .into_result();
         assert_eq!(
             result,
             Ok(today().checked_add_days(chrono::Days::new(3)).unwrap())
@@ -529,7 +591,10 @@ mod tests {
 
     #[test]
     fn tomorrow() {
-        let result = date().then_ignore(end()).parse("tomorrow").into_result();
+        let result = date()
+            .then_ignore(end())
+            .parse_with_state("tomorrow", &mut state())
+            .into_result();
         assert_eq!(
             result,
             Ok(today().checked_add_days(chrono::Days::new(1)).unwrap())
@@ -538,7 +603,10 @@ mod tests {
 
     #[test]
     fn test_today() {
-        let result = date().then_ignore(end()).parse("today").into_result();
+        let result = date()
+            .then_ignore(end())
+            .parse_with_state("today", &mut state())
+            .into_result();
         assert_eq!(result, Ok(today()));
     }
 
@@ -546,7 +614,7 @@ mod tests {
     fn month_ago() {
         let result = date()
             .then_ignore(end())
-            .parse("2 months  ago")
+            .parse_with_state("2 months  ago", &mut state())
             .into_result();
         assert_eq!(
             result,
@@ -558,7 +626,7 @@ mod tests {
     fn weeks_ahead() {
         let result = date()
             .then_ignore(end())
-            .parse("3 weeks  ahead")
+            .parse_with_state("3 weeks  ahead", &mut state())
             .into_result();
         assert_eq!(
             result,
@@ -568,7 +636,10 @@ mod tests {
 
     #[test]
     fn yesterday() {
-        let result = date().then_ignore(end()).parse("yesterday").into_result();
+        let result = date()
+            .then_ignore(end())
+            .parse_with_state("yesterday", &mut state())
+            .into_result();
         assert_eq!(
             result,
             Ok(today().checked_sub_days(chrono::Days::new(1)).unwrap())
@@ -577,7 +648,10 @@ mod tests {
 
     #[test]
     fn last_week() {
-        let result = date().then_ignore(end()).parse("last week").into_result();
+        let result = date()
+            .then_ignore(end())
+            .parse_with_state("last week", &mut state())
+            .into_result();
         assert_eq!(
             result,
             Ok(today().checked_sub_days(chrono::Days::new(7)).unwrap())
@@ -586,7 +660,10 @@ mod tests {
 
     #[test]
     fn next_year() {
-        let result = date().then_ignore(end()).parse("next year").into_result();
+        let result = date()
+            .then_ignore(end())
+            .parse_with_state("next year", &mut state())
+            .into_result();
         assert_eq!(
             result,
             Ok(today().checked_add_months(chrono::Months::new(12)).unwrap())
@@ -595,13 +672,19 @@ mod tests {
 
     #[test]
     fn just_day() {
-        let result = date().then_ignore(end()).parse("21").into_result();
+        let result = date()
+            .then_ignore(end())
+            .parse_with_state("21", &mut state())
+            .into_result();
         assert_eq!(result, Ok(today().with_day(21).unwrap()));
     }
 
     #[test]
     fn just_day_err() {
-        let result = date().then_ignore(end()).parse("32").into_result();
+        let result = date()
+            .then_ignore(end())
+            .parse_with_state("32", &mut state())
+            .into_result();
         assert!(result.is_err());
     }
 }
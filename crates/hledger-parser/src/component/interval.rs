@@ -36,7 +36,7 @@ pub fn interval<'a>() -> impl Parser<'a, &'a str, Interval, extra::Full<Rich<'a,
         just("monthly").to(Interval::NthMonth(1)),
         just("bimonthly").to(Interval::NthMonth(2)),
         just("quarterly").to(Interval::NthQuarter(1)),
-        just("yearly").to(Interval::NthQuarter(1)),
+        just("yearly").to(Interval::NthYear(1)),
     ]);
 
     choice((word, every(), day_of_week()))
@@ -278,6 +278,12 @@ mod tests {
         assert_eq!(result, Ok(Interval::NthYear(10)));
     }
 
+    #[test]
+    fn yearly() {
+        let result = interval().then_ignore(end()).parse("yearly").into_result();
+        assert_eq!(result, Ok(Interval::NthYear(1)));
+    }
+
     #[test]
     fn every_weekday() {
         let result = interval()
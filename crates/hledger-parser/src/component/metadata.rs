@@ -0,0 +1,124 @@
+use chumsky::prelude::*;
+
+use crate::component::whitespace::whitespace;
+use crate::state::State;
+
+/// Comment, tags and indented subdirectives following a declaration:
+///
+/// ```text
+/// payee Whole Foods  ; category: groceries
+///     ; online: yes
+///     format ...
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Metadata {
+    /// Comment lines, without the leading `;`.
+    pub comment: Option<String>,
+    /// `name: value` tags found in the comment, in order.
+    pub tags: Vec<(String, String)>,
+    /// Indented lines that are not comments.
+    pub subdirectives: Vec<String>,
+}
+
+enum Line {
+    Comment(String),
+    Subdirective(String),
+}
+
+// name: value, tags are separated by commas and the name is the word before the colon
+pub(crate) fn tags(comment: &str) -> Vec<(String, String)> {
+    comment
+        .split(',')
+        .filter_map(|segment| {
+            let (before, value) = segment.split_once(':')?;
+            let name = before.rsplit(char::is_whitespace).next()?;
+            (!name.is_empty()).then(|| (name.to_string(), value.trim().to_string()))
+        })
+        .collect()
+}
+
+/// Parses the rest of a declaration line and the indented lines that follow it.
+pub fn metadata<'a>() -> impl Parser<'a, &'a str, Metadata, extra::Full<Rich<'a, char>, State, ()>>
+{
+    let comment = || {
+        just(";").ignore_then(
+            any()
+                .and_is(text::newline().not())
+                .repeated()
+                .collect::<String>(),
+        )
+    };
+    let subdirective = any()
+        .and_is(text::newline().not())
+        .and_is(just(";").not())
+        .then(any().and_is(text::newline().not()).repeated())
+        .to_slice();
+    let indented =
+        text::newline()
+            .ignore_then(whitespace().repeated().at_least(1))
+            .ignore_then(comment().map(Line::Comment).or(
+                subdirective.map(|line: &str| Line::Subdirective(line.trim_end().to_string())),
+            ));
+    whitespace()
+        .repeated()
+        .ignore_then(comment().or_not())
+        .then(indented.repeated().collect::<Vec<_>>())
+        .map(|(first, lines)| {
+            let mut comments = first.into_iter().collect::<Vec<_>>();
+            let mut subdirectives = Vec::new();
+            for line in lines {
+                match line {
+                    Line::Comment(comment) => comments.push(comment),
+                    Line::Subdirective(subdirective) => subdirectives.push(subdirective),
+                }
+            }
+            let comments = comments
+                .iter()
+                .map(|comment| comment.trim())
+                .collect::<Vec<_>>();
+            Metadata {
+                comment: (!comments.is_empty()).then(|| comments.join("\n")),
+                tags: comments.iter().flat_map(|comment| tags(comment)).collect(),
+                subdirectives,
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty() {
+        let result = metadata().then_ignore(end()).parse("  ").into_result();
+        assert_eq!(result, Ok(Metadata::default()));
+    }
+
+    #[test]
+    fn full() {
+        let result = metadata()
+            .then_ignore(end())
+            .parse("  ; category: groceries, online:yes\n    ; a note\n    format 1.00 EUR")
+            .into_result();
+        assert_eq!(
+            result,
+            Ok(Metadata {
+                comment: Some(String::from("category: groceries, online:yes\na note")),
+                tags: vec![
+                    (String::from("category"), String::from("groceries")),
+                    (String::from("online"), String::from("yes")),
+                ],
+                subdirectives: vec![String::from("format 1.00 EUR")],
+            })
+        );
+    }
+
+    #[test]
+    fn tag_after_text() {
+        assert_eq!(
+            tags("bought at the store type: food"),
+            vec![(String::from("type"), String::from("food"))]
+        );
+        assert!(tags("no tags here").is_empty());
+    }
+}
@@ -1,5 +1,3 @@
-use std::time::SystemTime;
-
 use chrono::Datelike;
 use chumsky::prelude::*;
 
@@ -27,13 +25,6 @@ pub fn period<'a>() -> impl Parser<'a, &'a str, Period, extra::Full<Rich<'a, cha
     .map(|(begin, end)| Period { begin, end })
 }
 
-// returns today's date
-fn today() -> chrono::NaiveDate {
-    let current_time = SystemTime::now();
-    let datetime: chrono::DateTime<chrono::Local> = current_time.into();
-    datetime.date_naive()
-}
-
 // 2009Q1
 fn year_quarter<'a>() -> impl Parser<
     'a,
@@ -75,11 +66,11 @@ fn quarter<'a>() -> impl Parser<
         .map(|s: char| s.to_string())
         .from_str::<u32>()
         .unwrapped()
-        .map(|q| {
-            (
-                today().with_month((q - 1) * 3 + 1).unwrap().with_day(1),
-                today().with_month((q - 1) * 3 + 4).unwrap().with_day(1),
-            )
+        .map_with(|q, e| {
+            let state: &mut State = e.state();
+            let begin = chrono::NaiveDate::from_ymd_opt(state.today.year(), (q - 1) * 3 + 1, 1);
+            let end = begin.and_then(|begin| begin.checked_add_months(chrono::Months::new(3)));
+            (begin, end)
         })
 }
 
@@ -336,12 +327,40 @@ mod tests {
 
     #[test]
     fn quarter() {
-        let result = period().then_ignore(end()).parse("q3").into_result();
+        let mut state = State {
+            year: 2024,
+            today: chrono::NaiveDate::from_ymd_opt(2024, 10, 15).unwrap(),
+            ..State::default()
+        };
+        let result = period()
+            .then_ignore(end())
+            .parse_with_state("q3", &mut state)
+            .into_result();
+        assert_eq!(
+            result,
+            Ok(Period {
+                begin: chrono::NaiveDate::from_ymd_opt(2024, 7, 1),
+                end: chrono::NaiveDate::from_ymd_opt(2024, 10, 1),
+            })
+        );
+    }
+
+    #[test]
+    fn last_quarter() {
+        let mut state = State {
+            year: 2024,
+            today: chrono::NaiveDate::from_ymd_opt(2024, 10, 15).unwrap(),
+            ..State::default()
+        };
+        let result = period()
+            .then_ignore(end())
+            .parse_with_state("q4", &mut state)
+            .into_result();
         assert_eq!(
             result,
             Ok(Period {
-                begin: today().with_month(7).unwrap().with_day(1),
-                end: today().with_month(10).unwrap().with_day(1),
+                begin: chrono::NaiveDate::from_ymd_opt(2024, 10, 1),
+                end: chrono::NaiveDate::from_ymd_opt(2025, 1, 1),
             })
         );
     }
@@ -48,6 +48,7 @@ mod tests {
                 commodity: String::from("$"),
                 quantity: Decimal::new(135, 2),
                 price: None,
+                ..Amount::default()
             }))
         );
     }
@@ -64,6 +65,7 @@ mod tests {
                 commodity: String::from("$"),
                 quantity: Decimal::new(135, 2),
                 price: None,
+                ..Amount::default()
             }))
         );
     }
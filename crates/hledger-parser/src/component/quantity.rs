@@ -3,73 +3,240 @@ use rust_decimal::Decimal;
 
 use crate::state::State;
 
-pub fn quantity<'a>() -> impl Parser<'a, &'a str, Decimal, extra::Full<Rich<'a, char>, State, ()>> {
-    let digits = any()
-        .filter(|c: &char| c.is_ascii_digit())
-        .repeated()
-        .at_least(1)
-        .collect::<String>();
+/// How the digits of a quantity were written, to display quantities the way they were entered.
+/// The number of decimal places is kept by the quantity itself.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct Style {
+    /// Decimal mark, if the quantity was written with one.
+    pub decimal_mark: Option<char>,
+    /// Digit group mark and sizes of the digit groups, from the decimal mark leftwards. The last
+    /// size repeats, so `1,00,000` has sizes `[3, 2]`.
+    pub digit_groups: Option<(char, Vec<usize>)>,
+}
+
+impl Style {
+    /// Formats the quantity with this style.
+    #[must_use]
+    pub fn format(&self, quantity: &Decimal) -> String {
+        let formatted = quantity.abs().to_string();
+        let (integer, fraction) = formatted
+            .split_once('.')
+            .map_or((formatted.as_str(), None), |(integer, fraction)| {
+                (integer, Some(fraction))
+            });
+        let mut groups = Vec::new();
+        let mut rest = integer;
+        if let Some((_, sizes)) = &self.digit_groups {
+            let mut sizes = sizes
+                .iter()
+                .chain(std::iter::repeat(sizes.last().unwrap_or(&3)));
+            while let Some(size) = sizes.next().filter(|size| rest.len() > **size) {
+                groups.push(&rest[rest.len() - size..]);
+                rest = &rest[..rest.len() - size];
+            }
+        }
+        groups.push(rest);
+        groups.reverse();
+        let mark = self.digit_groups.as_ref().map_or(',', |(mark, _)| *mark);
+        let mut result = groups.join(&mark.to_string());
+        if quantity.is_sign_negative() && !quantity.is_zero() {
+            result.insert(0, '-');
+        }
+        if let Some(fraction) = fraction {
+            result.push(self.decimal_mark.unwrap_or('.'));
+            result.push_str(fraction);
+        }
+        result
+    }
+}
+
+fn is_separator(token: &str) -> bool {
+    matches!(token, "." | "," | "'" | " ")
+}
+
+// digits and separators, with a single space allowed as a digit group mark when
+// `space_grouping` is set
+fn tokens<'a>(
+    space_grouping: bool,
+) -> impl Parser<'a, &'a str, Vec<String>, extra::Full<Rich<'a, char>, State, ()>> {
+    let digit = any().filter(|c: &char| c.is_ascii_digit());
+    let digits = digit.repeated().at_least(1).collect::<String>();
     let separator = one_of(",.").map(String::from);
+    let group_mark = just('\'')
+        .or(just(' ').filter(move |_| space_grouping))
+        .then_ignore(digit.rewind())
+        .map(String::from);
     digits
         .or(separator)
-        .repeated()
-        .at_least(1)
-        .collect::<Vec<_>>()
-        .try_map(|tokens, span| {
-            let mut places = 0_u32;
-            let mut mantissa = String::new();
-            let mut decimal_separator = None;
-            let mut thousands_separator = None;
-            let mut last_token_was_separator = false;
-            for token in tokens.iter().rev() {
-                let is_separator = token == "." || token == ",";
-                if is_separator {
-                    if last_token_was_separator {
-                        return Err(Rich::custom(span, "unexpected separator"));
-                    }
-                    last_token_was_separator = true;
-                    match (decimal_separator, thousands_separator) {
-                        (None, None) => {
-                            // assume first seen separator is a decimal separator
-                            decimal_separator.replace(token);
-                        }
-                        (Some(sep), None) if token == sep => {
-                            // if second separator encountered, and it's the same as decimal
-                            // separator, we are in the only thousands_separator handing case.
-                            places = 0;
-                            decimal_separator = None;
-                            thousands_separator.replace(token);
-                        }
-                        (Some(_), None) => {
-                            thousands_separator.replace(token);
-                        }
-                        (None | Some(_), Some(thousands_separator)) => {
-                            if token != thousands_separator {
-                                return Err(Rich::custom(
-                                    span,
-                                    "unexpected thousands separator: {token}",
-                                ));
-                            };
-                        }
-                    }
-                } else {
-                    last_token_was_separator = false;
-                    if decimal_separator.is_none() {
-                        places += u32::try_from(token.len()).unwrap();
-                    }
-                    mantissa = token.to_owned() + &mantissa;
+        .then(
+            digits
+                .or(separator)
+                .or(group_mark)
+                .repeated()
+                .collect::<Vec<_>>(),
+        )
+        .map(|(first, mut rest)| {
+            rest.insert(0, first);
+            rest
+        })
+}
+
+// 1E-6, 2.5e3
+fn exponent<'a>() -> impl Parser<'a, &'a str, i64, extra::Full<Rich<'a, char>, State, ()>> {
+    one_of("eE")
+        .ignore_then(one_of("+-").or_not())
+        .then(
+            any()
+                .filter(|c: &char| c.is_ascii_digit())
+                .repeated()
+                .at_least(1)
+                .collect::<String>(),
+        )
+        .try_map(|(sign, digits), span| {
+            let exponent = digits
+                .parse::<i64>()
+                .map_err(|_| Rich::custom(span, "exponent is too large"))?;
+            Ok(if sign == Some('-') {
+                -exponent
+            } else {
+                exponent
+            })
+        })
+}
+
+/// Parses a quantity, keeping the style it was written in. Digit groups may be separated by
+/// periods, commas, apostrophes or single spaces, and the quantity may have an exponent.
+pub fn styled_quantity<'a>(
+) -> impl Parser<'a, &'a str, (Decimal, Style), extra::Full<Rich<'a, char>, State, ()>> {
+    tokens(true)
+        .then(exponent().or_not())
+        .try_map_with(|(tokens, exponent), e| {
+            let state: &mut State = e.state();
+            parse(&tokens, exponent.unwrap_or(0), state.decimal_mark)
+                .map_err(|message| Rich::custom(e.span(), message))
+        })
+}
+
+/// Parses a quantity like [`styled_quantity`], except that spaces never separate digit groups.
+pub fn quantity<'a>() -> impl Parser<'a, &'a str, Decimal, extra::Full<Rich<'a, char>, State, ()>> {
+    tokens(false)
+        .then(exponent().or_not())
+        .try_map_with(|(tokens, exponent), e| {
+            let state: &mut State = e.state();
+            parse(&tokens, exponent.unwrap_or(0), state.decimal_mark)
+                .map(|(quantity, _)| quantity)
+                .map_err(|message| Rich::custom(e.span(), message))
+        })
+}
+
+fn parse(
+    tokens: &[String],
+    exponent: i64,
+    decimal_mark: Option<char>,
+) -> Result<(Decimal, Style), &'static str> {
+    let decimal_mark = match decimal_mark {
+        Some(decimal_mark) => Some(decimal_mark),
+        None => guess_decimal_mark(tokens)?,
+    };
+    with_decimal_mark(tokens, exponent, decimal_mark)
+}
+
+// Guesses which separator is a decimal mark:
+// - apostrophes and spaces are always digit group marks, so the other separator is a decimal mark
+// - a separator that occurs more than once is a digit group mark
+// - otherwise the last separator is a decimal mark
+fn guess_decimal_mark(tokens: &[String]) -> Result<Option<char>, &'static str> {
+    let separators = tokens
+        .iter()
+        .filter(|token| is_separator(token))
+        .filter_map(|token| token.chars().next())
+        .collect::<Vec<_>>();
+    if separators.iter().any(|c| *c == '\'' || *c == ' ') {
+        let mut marks = separators.iter().filter(|c| **c == '.' || **c == ',');
+        return match (marks.next(), marks.next()) {
+            (Some(mark), None) => Ok(Some(*mark)),
+            (None, _) => Ok(None),
+            (Some(_), Some(_)) => Err("unexpected decimal mark"),
+        };
+    }
+    match separators.last() {
+        None => Ok(None),
+        Some(last) if separators.iter().filter(|c| *c == last).count() > 1 => Ok(None),
+        Some(last) => Ok(Some(*last)),
+    }
+}
+
+// parses number with a known decimal mark, any other separator is treated as digit group mark
+fn with_decimal_mark(
+    tokens: &[String],
+    exponent: i64,
+    decimal_mark: Option<char>,
+) -> Result<(Decimal, Style), &'static str> {
+    let mut places = None::<u32>;
+    let mut mantissa = String::new();
+    let mut last_token_was_separator = false;
+    let mut group_mark = None::<char>;
+    let mut groups = Vec::new();
+    for token in tokens {
+        if is_separator(token) {
+            if last_token_was_separator {
+                return Err("unexpected separator");
+            }
+            last_token_was_separator = true;
+            let mark = token.chars().next().expect("separator is not empty");
+            if Some(mark) == decimal_mark {
+                if places.is_some() {
+                    return Err("unexpected decimal mark");
                 }
+                places = Some(0);
+            } else if places.is_some() {
+                return Err("unexpected digit group mark after decimal mark");
+            } else if group_mark.is_some_and(|group_mark| group_mark != mark) {
+                return Err("unexpected digit group mark");
+            } else {
+                group_mark = Some(mark);
             }
-
-            if decimal_separator.is_none() {
-                places = 0;
+        } else {
+            if let Some(places) = places.as_mut() {
+                *places += u32::try_from(token.len()).map_err(|_| "number is too long")?;
+            } else {
+                groups.push(token.len());
             }
+            last_token_was_separator = false;
+            mantissa.push_str(token);
+        }
+    }
+    let style = Style {
+        decimal_mark: decimal_mark.filter(|_| places.is_some()),
+        digit_groups: group_mark.map(|mark| (mark, digit_group_sizes(&groups))),
+    };
+    to_decimal(&mantissa, places.unwrap_or(0), exponent).map(|quantity| (quantity, style))
+}
 
-            match mantissa.parse::<i64>() {
-                Ok(mantissa) => Ok(Decimal::new(mantissa, places)),
-                Err(_) => Err(Rich::custom(span, "failed to parse number")),
-            }
-        })
+// sizes of all but the leftmost group, from the right, without repetitions of the last size
+fn digit_group_sizes(groups: &[usize]) -> Vec<usize> {
+    let mut sizes = groups.iter().skip(1).rev().copied().collect::<Vec<_>>();
+    while sizes.len() > 1 && sizes[sizes.len() - 1] == sizes[sizes.len() - 2] {
+        sizes.pop();
+    }
+    sizes
+}
+
+fn to_decimal(mantissa: &str, places: u32, exponent: i64) -> Result<Decimal, &'static str> {
+    let mantissa = mantissa
+        .parse::<i128>()
+        .map_err(|_| "failed to parse number")?;
+    let scale = i64::from(places) - exponent;
+    if let Ok(scale) = u32::try_from(scale) {
+        Decimal::try_from_i128_with_scale(mantissa, scale).map_err(|_| "number is too precise")
+    } else {
+        let multiplier = u32::try_from(-scale)
+            .ok()
+            .and_then(|power| 10_i128.checked_pow(power))
+            .and_then(|multiplier| mantissa.checked_mul(multiplier))
+            .ok_or("number is too large")?;
+        Decimal::try_from_i128_with_scale(multiplier, 0).map_err(|_| "number is too large")
+    }
 }
 
 #[cfg(test)]
@@ -170,4 +337,113 @@ mod tests {
         assert_eq!(result1, result2);
         assert_eq!(result2, Ok(Decimal::new(12_345_678, 3)));
     }
+
+    #[test]
+    fn with_decimal_mark() {
+        for (input, decimal_mark, expected) in [
+            ("1,234", '.', Decimal::new(1234, 0)),
+            ("1,234", ',', Decimal::new(1234, 3)),
+            ("1.234.567", ',', Decimal::new(1_234_567, 0)),
+            ("1.234,5", ',', Decimal::new(12345, 1)),
+        ] {
+            let mut state = State {
+                decimal_mark: Some(decimal_mark),
+                ..State::default()
+            };
+            let result = quantity()
+                .then_ignore(end())
+                .parse_with_state(input, &mut state)
+                .into_result();
+            assert_eq!(result, Ok(expected), "{input}");
+        }
+    }
+
+    #[test]
+    fn with_decimal_mark_err() {
+        let mut state = State {
+            decimal_mark: Some('.'),
+            ..State::default()
+        };
+        let result = quantity()
+            .then_ignore(end())
+            .parse_with_state("1.234,5", &mut state)
+            .into_result();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn space_and_apostrophe_grouping() {
+        for (input, expected) in [
+            ("1 000,00", Decimal::new(100_000, 2)),
+            ("1'000.00", Decimal::new(100_000, 2)),
+            ("1 234 567", Decimal::new(1_234_567, 0)),
+            ("1'234", Decimal::new(1234, 0)),
+        ] {
+            let result = quantity_with_style(input).map(|(quantity, _)| quantity);
+            assert_eq!(result, Ok(expected), "{input}");
+        }
+    }
+
+    #[test]
+    fn no_space_grouping_in_quantity() {
+        let result = quantity().parse("1 000").into_result();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn indian_grouping() {
+        let result = quantity_with_style("1,00,000.50");
+        assert_eq!(
+            result,
+            Ok((
+                Decimal::new(10_000_050, 2),
+                Style {
+                    decimal_mark: Some('.'),
+                    digit_groups: Some((',', vec![3, 2])),
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn exponent() {
+        for (input, expected) in [
+            ("1E-6", Decimal::new(1, 6)),
+            ("1.5e3", Decimal::new(1500, 0)),
+            ("2E+2", Decimal::new(200, 0)),
+            ("1,000.5E-1", Decimal::new(10005, 2)),
+        ] {
+            let result = quantity_with_style(input).map(|(quantity, _)| quantity);
+            assert_eq!(result, Ok(expected), "{input}");
+        }
+    }
+
+    #[test]
+    fn mixed_group_marks() {
+        let result = quantity_with_style("1'000 000");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn format_with_style() {
+        for (input, expected) in [
+            ("1 000,00", "1 000,00"),
+            ("-1,00,000.5", "-1,00,000.5"),
+            ("12.345.678", "12.345.678"),
+            ("1234.5", "1234.5"),
+        ] {
+            let (mut quantity, style) = quantity_with_style(input.trim_start_matches('-')).unwrap();
+            if input.starts_with('-') {
+                quantity.set_sign_negative(true);
+            }
+            assert_eq!(style.format(&quantity), expected, "{input}");
+        }
+    }
+
+    fn quantity_with_style(input: &str) -> Result<(Decimal, Style), Vec<Rich<'_, char>>> {
+        styled_quantity()
+            .then_ignore(end())
+            .parse(input)
+            .into_result()
+    }
 }
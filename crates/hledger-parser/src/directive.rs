@@ -1,12 +1,16 @@
 mod account;
+mod alias;
+mod apply_account;
 mod auto_postings;
 mod commodity;
 mod decimal_mark;
 mod include;
+mod ledger;
 mod payee;
 mod price;
 mod tag;
 mod transaction;
+mod unsupported;
 mod year;
 
 use chumsky::prelude::*;
@@ -14,6 +18,8 @@ use chumsky::prelude::*;
 use crate::component::comment::{block, inline, line};
 use crate::component::whitespace::whitespace;
 use crate::directive::account::account;
+use crate::directive::alias::{alias, end_aliases};
+use crate::directive::apply_account::{apply_account, end_apply_account};
 use crate::directive::auto_postings::auto_postings;
 use crate::directive::commodity::commodity;
 use crate::directive::decimal_mark::decimal_mark;
@@ -25,7 +31,12 @@ use crate::directive::year::year;
 use crate::state::State;
 
 pub use crate::directive::account::Account;
-pub use crate::directive::auto_postings::{AutoPosting, AutosPostingRule};
+pub(crate) use crate::directive::alias::rewrite;
+pub use crate::directive::alias::Alias;
+pub use crate::directive::apply_account::ApplyAccount;
+pub use crate::directive::auto_postings::{
+    AutoAmount, AutoPosting, AutosPostingRule, Expression as AutoAmountExpression,
+};
 pub use crate::directive::commodity::Commodity;
 pub use crate::directive::decimal_mark::DecimalMark;
 pub use crate::directive::include::{Format, Include};
@@ -35,20 +46,26 @@ pub use crate::directive::tag::Tag;
 pub use crate::directive::transaction::{
     Assertion, Periodic as PeriodicTransaction, Posting, Simple as Transaction,
 };
+pub use crate::directive::unsupported::Unsupported;
 pub use crate::directive::year::Year;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Directive {
     Account(Account),
+    Alias(Alias),
+    ApplyAccount(ApplyAccount),
     AutoPostings(AutosPostingRule),
     Commodity(Commodity),
     DecimalMark(DecimalMark),
+    EndAliases,
+    EndApplyAccount,
     Include(Include),
     Payee(Payee),
     Price(Price),
     Tag(Tag),
     Transaction(Transaction),
     PeriodicTransaction(PeriodicTransaction),
+    Unsupported(Unsupported),
     Year(Year),
 }
 
@@ -60,9 +77,15 @@ pub fn directive<'a>() -> impl Parser<'a, &'a str, Directive, extra::Full<Rich<'
     // see https://github.com/zesterer/chumsky/issues/13
     choice((
         account().map(Directive::Account).boxed(),
+        alias().map(Directive::Alias).boxed(),
+        apply_account().map(Directive::ApplyAccount).boxed(),
         auto_postings().map(Directive::AutoPostings).boxed(),
         commodity().map(Directive::Commodity).boxed(),
         decimal_mark().map(Directive::DecimalMark).boxed(),
+        end_aliases().map(|()| Directive::EndAliases).boxed(),
+        end_apply_account()
+            .map(|()| Directive::EndApplyAccount)
+            .boxed(),
         include().map(Directive::Include).boxed(),
         payee().map(Directive::Payee).boxed(),
         price().map(Directive::Price).boxed(),
@@ -79,6 +102,7 @@ pub fn directives<'a>(
 ) -> impl Parser<'a, &'a str, Vec<Directive>, extra::Full<Rich<'a, char>, State, ()>> {
     choice((
         directive().map(Some),
+        ledger::directive(),
         inline().map(|_| None),
         line().map(|_| None),
         block().map(|_| None),
@@ -17,7 +17,12 @@ pub fn account<'a>() -> impl Parser<'a, &'a str, Account, extra::Full<Rich<'a, c
         .then_ignore(
             end_of_line_prefixed(2), // The two-space requirement for same-line account comments is because ; is allowed in account names.
         )
-        .map(|account_name| Account { account_name })
+        .map_with(|account_name, e| {
+            let state: &mut State = e.state();
+            Account {
+                account_name: state.account_name(account_name),
+            }
+        })
 }
 
 #[cfg(test)]
@@ -0,0 +1,288 @@
+use chumsky::prelude::*;
+
+use crate::component::account_name::AccountName;
+use crate::component::whitespace::whitespace;
+use crate::state::State;
+use crate::utils::end_of_line;
+
+#[derive(Clone, Debug)]
+pub enum Alias {
+    /// `alias OLD = NEW`: replaces an account name and its subaccounts' prefix.
+    Simple { from: String, to: String },
+    /// `alias /REGEX/ = REPLACEMENT`: replaces every case-insensitive match in the full account
+    /// name. Replacement may refer to match groups with `\1`.
+    Regex {
+        regex: regex::Regex,
+        replacement: String,
+    },
+}
+
+impl PartialEq for Alias {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (
+                Alias::Simple { from, to },
+                Alias::Simple {
+                    from: other_from,
+                    to: other_to,
+                },
+            ) => from == other_from && to == other_to,
+            (
+                Alias::Regex { regex, replacement },
+                Alias::Regex {
+                    regex: other_regex,
+                    replacement: other_replacement,
+                },
+            ) => regex.as_str() == other_regex.as_str() && replacement == other_replacement,
+            _ => false,
+        }
+    }
+}
+
+impl Alias {
+    /// Applies the alias to the full account name, returning it unchanged if it does not match.
+    #[must_use]
+    pub fn apply(&self, account_name: &str) -> String {
+        match self {
+            Alias::Simple { from, to } => {
+                if account_name == from {
+                    to.clone()
+                } else if let Some(rest) = account_name
+                    .strip_prefix(from.as_str())
+                    .and_then(|rest| rest.strip_prefix(':'))
+                {
+                    format!("{to}:{rest}")
+                } else {
+                    account_name.to_string()
+                }
+            }
+            Alias::Regex { regex, replacement } => regex
+                .replace_all(account_name, to_regex_replacement(replacement))
+                .into_owned(),
+        }
+    }
+}
+
+// hledger uses \1 for back references, regex crate uses ${1}.
+fn to_regex_replacement(replacement: &str) -> String {
+    let mut result = String::with_capacity(replacement.len());
+    let mut chars = replacement.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('\\', Some(d)) if d.is_ascii_digit() => {
+                result.push_str("${");
+                result.push(*d);
+                result.push('}');
+                chars.next();
+            }
+            ('$', _) => result.push_str("$$"),
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+/// Rewrites the account name with all aliases, most recently defined first, each one applied to
+/// the result of the previous.
+pub fn rewrite(aliases: &[Alias], account_name: AccountName) -> AccountName {
+    if aliases.is_empty() {
+        return account_name;
+    }
+    let rewritten = aliases
+        .iter()
+        .rev()
+        .fold(account_name.to_string(), |name, alias| alias.apply(&name));
+    AccountName::from_parts(
+        &rewritten
+            .split(':')
+            .map(ToString::to_string)
+            .collect::<Vec<_>>(),
+    )
+}
+
+pub fn alias<'a>() -> impl Parser<'a, &'a str, Alias, extra::Full<Rich<'a, char>, State, ()>> {
+    let to = any()
+        .and_is(text::newline().not())
+        .and_is(just(";").not())
+        .repeated()
+        .at_least(1)
+        .collect::<String>()
+        .map(|to| to.trim().to_string());
+    let regex = any()
+        .and_is(just("/").not())
+        .and_is(text::newline().not())
+        .repeated()
+        .at_least(1)
+        .collect::<String>()
+        .delimited_by(just("/"), just("/"))
+        .then_ignore(whitespace().repeated())
+        .then_ignore(just("="))
+        .then_ignore(whitespace().repeated())
+        .then(to)
+        .try_map(|(pattern, replacement), span| {
+            let regex = regex::RegexBuilder::new(&pattern)
+                .case_insensitive(true)
+                .build()
+                .map_err(|error| Rich::custom(span, error))?;
+            Ok(Alias::Regex { regex, replacement })
+        });
+    // must not start with a slash, to not accept invalid regular expressions as simple aliases
+    let simple = any()
+        .and_is(text::newline().not())
+        .and_is(just("=").not())
+        .and_is(just("/").not())
+        .then(
+            any()
+                .and_is(text::newline().not())
+                .and_is(just("=").not())
+                .repeated()
+                .collect::<String>(),
+        )
+        .map(|(first, rest): (char, String)| format!("{first}{rest}"))
+        .then_ignore(just("="))
+        .then_ignore(whitespace().repeated())
+        .then(to)
+        .map(|(from, to)| Alias::Simple {
+            from: from.trim().to_string(),
+            to,
+        });
+    just("alias")
+        .ignore_then(whitespace().repeated().at_least(1))
+        .ignore_then(regex.or(simple))
+        .then_ignore(end_of_line())
+        .map_with(|alias, e| {
+            let state: &mut State = e.state();
+            state.aliases.push(alias.clone());
+            alias
+        })
+}
+
+pub fn end_aliases<'a>() -> impl Parser<'a, &'a str, (), extra::Full<Rich<'a, char>, State, ()>> {
+    just("end")
+        .ignore_then(whitespace().repeated().at_least(1))
+        .ignore_then(just("aliases"))
+        .then_ignore(end_of_line())
+        .map_with(|_, e| {
+            let state: &mut State = e.state();
+            state.aliases.clear();
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simple() {
+        let mut state = State::default();
+        let result = alias()
+            .then_ignore(end())
+            .parse_with_state("alias checking = assets:bank:checking", &mut state)
+            .into_result();
+        let expected = Alias::Simple {
+            from: String::from("checking"),
+            to: String::from("assets:bank:checking"),
+        };
+        assert_eq!(result, Ok(expected.clone()));
+        assert_eq!(state.aliases, vec![expected]);
+    }
+
+    #[test]
+    fn regex() {
+        let result = alias()
+            .then_ignore(end())
+            .parse("alias /^(.+):bank:([^:]+):(.*)/ = \\1:\\2 \\3  ; comment")
+            .into_result();
+        assert_eq!(
+            result,
+            Ok(Alias::Regex {
+                regex: regex::Regex::new("^(.+):bank:([^:]+):(.*)").unwrap(),
+                replacement: String::from("\\1:\\2 \\3"),
+            })
+        );
+    }
+
+    #[test]
+    fn invalid_regex() {
+        let result = alias()
+            .then_ignore(end())
+            .parse("alias /(/ = a")
+            .into_result();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn end_clears_aliases() {
+        let mut state = State::default();
+        let result = alias()
+            .then_ignore(text::newline())
+            .then(end_aliases())
+            .then_ignore(end())
+            .parse_with_state("alias a = b\nend aliases", &mut state)
+            .into_result();
+        assert!(result.is_ok());
+        assert!(state.aliases.is_empty());
+    }
+
+    #[test]
+    fn apply_simple() {
+        let alias = Alias::Simple {
+            from: String::from("checking"),
+            to: String::from("assets:checking"),
+        };
+        assert_eq!(alias.apply("checking"), "assets:checking");
+        assert_eq!(alias.apply("checking:joint"), "assets:checking:joint");
+        assert_eq!(alias.apply("checkings"), "checkings");
+    }
+
+    #[test]
+    fn apply_regex() {
+        let alias = Alias::Regex {
+            regex: regex::Regex::new("^(.+):bank:([^:]+):(.*)").unwrap(),
+            replacement: String::from("\\1:\\2 \\3"),
+        };
+        assert_eq!(
+            alias.apply("assets:bank:wells fargo:checking"),
+            "assets:wells fargo checking"
+        );
+    }
+
+    #[test]
+    fn rewrite_order() {
+        let aliases = vec![
+            Alias::Simple {
+                from: String::from("a"),
+                to: String::from("b"),
+            },
+            Alias::Simple {
+                from: String::from("c"),
+                to: String::from("a"),
+            },
+        ];
+        let result = rewrite(&aliases, AccountName::from_parts(&[String::from("c")]));
+        assert_eq!(result, AccountName::from_parts(&[String::from("b")]));
+    }
+
+    #[test]
+    fn scoped() {
+        let directives = crate::parse(
+            "alias a = b
+2020-01-01 first
+    a  1
+    c
+end aliases
+2020-01-02 second
+    a  1
+    c",
+        )
+        .unwrap();
+        let accounts = directives
+            .iter()
+            .filter_map(|directive| match directive {
+                crate::Directive::Transaction(tx) => Some(tx.postings[0].account_name.to_string()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(accounts, vec![String::from("b"), String::from("a")]);
+    }
+}
@@ -0,0 +1,98 @@
+use chumsky::prelude::*;
+
+use crate::component::account_name::{account_name, AccountName};
+use crate::component::whitespace::whitespace;
+use crate::state::State;
+use crate::utils::{end_of_line, end_of_line_prefixed};
+
+/// Parent account prefixed to the accounts of the following entries, until the matching
+/// `end apply account`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ApplyAccount(pub AccountName);
+
+pub fn apply_account<'a>(
+) -> impl Parser<'a, &'a str, ApplyAccount, extra::Full<Rich<'a, char>, State, ()>> {
+    just("apply")
+        .ignore_then(whitespace().repeated().at_least(1))
+        .ignore_then(just("account"))
+        .ignore_then(whitespace().repeated().at_least(1))
+        .ignore_then(account_name())
+        .then_ignore(end_of_line_prefixed(2))
+        .map_with(|account_name, e| {
+            let state: &mut State = e.state();
+            state.apply_accounts.push(account_name.clone());
+            ApplyAccount(account_name)
+        })
+}
+
+pub fn end_apply_account<'a>(
+) -> impl Parser<'a, &'a str, (), extra::Full<Rich<'a, char>, State, ()>> {
+    just("end")
+        .ignore_then(whitespace().repeated().at_least(1))
+        .ignore_then(just("apply"))
+        .ignore_then(whitespace().repeated().at_least(1))
+        .ignore_then(just("account"))
+        .then_ignore(end_of_line())
+        .map_with(|_, e| {
+            let state: &mut State = e.state();
+            state.apply_accounts.pop();
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_update_state() {
+        let mut state = State::default();
+        let result = apply_account()
+            .then_ignore(end())
+            .parse_with_state("apply account assets:bank", &mut state)
+            .into_result();
+        let expected = AccountName::from_parts(&[String::from("assets"), String::from("bank")]);
+        assert_eq!(result, Ok(ApplyAccount(expected.clone())));
+        assert_eq!(state.apply_accounts, vec![expected]);
+
+        let result = end_apply_account()
+            .then_ignore(end())
+            .parse_with_state("end apply account  ; done", &mut state)
+            .into_result();
+        assert_eq!(result, Ok(()));
+        assert!(state.apply_accounts.is_empty());
+    }
+
+    #[test]
+    fn prefixes_postings() {
+        let directives = crate::parse(
+            "apply account business
+apply account bank
+2024-01-01 test
+    checking  $1
+    (equity)
+end apply account
+account cash",
+        )
+        .unwrap();
+        let accounts = directives
+            .iter()
+            .flat_map(|directive| match directive {
+                crate::Directive::Transaction(transaction) => transaction
+                    .postings
+                    .iter()
+                    .map(|posting| posting.account_name.to_string())
+                    .collect(),
+                crate::Directive::Account(account) => vec![account.account_name.to_string()],
+                _ => Vec::new(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            accounts,
+            vec![
+                "business:bank:checking",
+                "business:bank:equity",
+                "business:cash"
+            ]
+        );
+    }
+}
@@ -3,8 +3,14 @@ use chumsky::prelude::*;
 use crate::component::account_name::{account_name, AccountName};
 use crate::component::amount::{amount, Amount};
 use crate::component::comment::inline;
+use crate::component::commodity::commodity;
+use crate::component::price::AmountPrice;
+use crate::component::quantity::styled_quantity;
 use crate::component::query::{query, Term};
 use crate::component::whitespace::whitespace;
+use crate::directive::ledger::query as ledger_query;
+use crate::directive::transaction::Posting;
+use crate::options::Dialect;
 use crate::state::State;
 use crate::utils::end_of_line;
 
@@ -12,41 +18,329 @@ use crate::utils::end_of_line;
 pub struct AutosPostingRule {
     pub query: Vec<Term>,
     pub postings: Vec<AutoPosting>,
+    pub position: std::ops::Range<usize>,
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct AutoPosting {
     pub account_name: AccountName,
     pub is_virtual: bool,
-    pub amount: Amount,
-    pub is_mul: bool,
+    pub is_balanced_virtual: bool,
+    pub amount: AutoAmount,
+}
+
+/// Amount of an auto posting, computed from the matched posting.
+#[derive(Clone, Debug, PartialEq)]
+pub enum AutoAmount {
+    /// `$2` is used as is, `2` gets the commodity of the matched posting.
+    Amount(Amount),
+    /// `*2` multiplies the matched posting's amount and total price, `*$2` also replaces its
+    /// commodity.
+    Multiplier(Amount),
+    /// `(amount * 0.5 + $1)` is evaluated with the matched posting's amount.
+    Expression(Expression),
+}
+
+/// Arithmetic expression of an auto posting amount.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expression {
+    /// Literal amount, possibly without a commodity.
+    Amount(Amount),
+    /// Amount of the matched posting: `amount` or `a`.
+    Matched,
+    Negate(Box<Expression>),
+    Add(Box<Expression>, Box<Expression>),
+    Subtract(Box<Expression>, Box<Expression>),
+    Multiply(Box<Expression>, Box<Expression>),
+    Divide(Box<Expression>, Box<Expression>),
+}
+
+impl Expression {
+    /// Evaluates the expression for one amount of the matched posting.
+    ///
+    /// Amounts without a commodity are numbers: they can multiply and divide amounts, and take
+    /// the commodity of the other operand when added. Returns `None` when amounts in different
+    /// commodities are added, two commodities are multiplied, or on division by zero or overflow.
+    #[must_use]
+    pub fn evaluate(&self, matched: &Amount) -> Option<Amount> {
+        match self {
+            Expression::Amount(amount) => Some(Amount {
+                price: None,
+                ..amount.clone()
+            }),
+            Expression::Matched => Some(Amount {
+                price: None,
+                ..matched.clone()
+            }),
+            Expression::Negate(expression) => expression.evaluate(matched).map(|amount| Amount {
+                quantity: -amount.quantity,
+                ..amount
+            }),
+            Expression::Add(left, right) | Expression::Subtract(left, right) => {
+                let left = left.evaluate(matched)?;
+                let right = right.evaluate(matched)?;
+                let commodity = common_commodity(&left, &right)?;
+                let quantity = if matches!(self, Expression::Add(_, _)) {
+                    left.quantity.checked_add(right.quantity)?
+                } else {
+                    left.quantity.checked_sub(right.quantity)?
+                };
+                Some(Amount {
+                    quantity,
+                    commodity,
+                    ..left
+                })
+            }
+            Expression::Multiply(left, right) => {
+                let left = left.evaluate(matched)?;
+                let right = right.evaluate(matched)?;
+                let quantity = left.quantity.checked_mul(right.quantity)?;
+                match (left.commodity.is_empty(), right.commodity.is_empty()) {
+                    (true, _) => Some(Amount { quantity, ..right }),
+                    (false, true) => Some(Amount { quantity, ..left }),
+                    (false, false) => None,
+                }
+            }
+            Expression::Divide(left, right) => {
+                let left = left.evaluate(matched)?;
+                let right = right.evaluate(matched)?;
+                if !right.commodity.is_empty() {
+                    return None;
+                }
+                Some(Amount {
+                    quantity: left.quantity.checked_div(right.quantity)?,
+                    ..left
+                })
+            }
+        }
+    }
+}
+
+fn common_commodity(left: &Amount, right: &Amount) -> Option<String> {
+    if left.commodity.is_empty() || left.commodity == right.commodity {
+        Some(right.commodity.clone())
+    } else if right.commodity.is_empty() {
+        Some(left.commodity.clone())
+    } else {
+        None
+    }
+}
+
+// amount of the matched posting with the multiplier applied to its quantity and total price
+fn multiply(matched: &Amount, multiplier: &Amount) -> Option<Amount> {
+    let quantity = matched.quantity.checked_mul(multiplier.quantity)?;
+    if !multiplier.commodity.is_empty() {
+        return Some(Amount {
+            quantity,
+            commodity: multiplier.commodity.clone(),
+            price: None,
+            style: multiplier.style.clone(),
+        });
+    }
+    let price = match matched.price.as_deref() {
+        Some(AmountPrice::Total(total)) => Some(Box::new(AmountPrice::Total(Amount {
+            quantity: total.quantity.checked_mul(multiplier.quantity)?,
+            ..total.clone()
+        }))),
+        price => price.cloned().map(Box::new),
+    };
+    Some(Amount {
+        quantity,
+        price,
+        ..matched.clone()
+    })
+}
+
+impl AutoAmount {
+    /// Returns amounts for the matched posting's amounts, or `None` when they can not be
+    /// computed.
+    #[must_use]
+    pub fn evaluate(&self, matched: &[Amount]) -> Option<Vec<Amount>> {
+        match self {
+            AutoAmount::Amount(amount) if amount.commodity.is_empty() => Some(vec![Amount {
+                commodity: matched
+                    .first()
+                    .map(|matched| matched.commodity.clone())
+                    .unwrap_or_default(),
+                ..amount.clone()
+            }]),
+            AutoAmount::Amount(amount) => Some(vec![amount.clone()]),
+            AutoAmount::Multiplier(multiplier) => matched
+                .iter()
+                .map(|matched| multiply(matched, multiplier))
+                .collect(),
+            AutoAmount::Expression(expression) => matched
+                .iter()
+                .map(|matched| {
+                    expression.evaluate(matched).map(|amount| {
+                        if amount.commodity.is_empty() {
+                            Amount {
+                                commodity: matched.commodity.clone(),
+                                ..amount
+                            }
+                        } else {
+                            amount
+                        }
+                    })
+                })
+                .collect(),
+        }
+    }
+}
+
+impl AutoPosting {
+    /// Returns the posting generated for the matched posting, or `None` when its amount can not
+    /// be computed.
+    #[must_use]
+    pub fn evaluate(&self, matched: &Posting) -> Option<Posting> {
+        let amount = self.amount.evaluate(&matched.amount)?;
+        Some(Posting {
+            status: None,
+            account_name: self.account_name.clone(),
+            is_virtual: self.is_virtual,
+            is_balanced_virtual: self.is_balanced_virtual,
+            is_amount_specified: !amount.is_empty(),
+            amount,
+            assertion: None,
+            is_generated: true,
+            comment: None,
+            tags: Vec::new(),
+            position: matched.position.clone(),
+        })
+    }
+}
+
+impl AutosPostingRule {
+    /// Returns postings generated for the posting matched by the rule's query, or `None` when
+    /// any of their amounts can not be computed.
+    #[must_use]
+    pub fn evaluate(&self, matched: &Posting) -> Option<Vec<Posting>> {
+        self.postings
+            .iter()
+            .map(|posting| posting.evaluate(matched))
+            .collect()
+    }
+}
+
+fn expression<'a>() -> impl Parser<'a, &'a str, Expression, extra::Full<Rich<'a, char>, State, ()>>
+{
+    recursive(|expression| {
+        let matched = just("amount")
+            .or(just("a"))
+            .then(any().filter(|c: &char| c.is_alphanumeric()).not().rewind())
+            .to(Expression::Matched);
+        // operands are unsigned, so that signs are parsed as operators
+        let operand = commodity()
+            .then_ignore(whitespace().repeated())
+            .then(styled_quantity())
+            .map(|(commodity, (quantity, style))| Amount {
+                quantity,
+                commodity,
+                price: None,
+                style,
+            })
+            .or(styled_quantity()
+                .then_ignore(whitespace().repeated())
+                .then(commodity())
+                .map(|((quantity, style), commodity)| Amount {
+                    quantity,
+                    commodity,
+                    price: None,
+                    style,
+                }));
+        let atom = choice((
+            matched,
+            operand.map(Expression::Amount),
+            expression.delimited_by(just("("), just(")")),
+        ))
+        .padded_by(whitespace().repeated());
+        let unary = just("-")
+            .ignore_then(whitespace().repeated())
+            .repeated()
+            .foldr(atom, |(), expression| {
+                Expression::Negate(Box::new(expression))
+            })
+            .boxed();
+        let product = unary
+            .clone()
+            .foldl(
+                one_of("*/").then(unary).repeated(),
+                |left, (operator, right)| {
+                    if operator == '*' {
+                        Expression::Multiply(Box::new(left), Box::new(right))
+                    } else {
+                        Expression::Divide(Box::new(left), Box::new(right))
+                    }
+                },
+            )
+            .boxed();
+        product.clone().foldl(
+            one_of("+-").then(product).repeated(),
+            |left, (operator, right)| {
+                if operator == '+' {
+                    Expression::Add(Box::new(left), Box::new(right))
+                } else {
+                    Expression::Subtract(Box::new(left), Box::new(right))
+                }
+            },
+        )
+    })
+}
+
+fn auto_amount<'a>() -> impl Parser<'a, &'a str, AutoAmount, extra::Full<Rich<'a, char>, State, ()>>
+{
+    choice((
+        expression()
+            .delimited_by(just("("), just(")"))
+            .map(AutoAmount::Expression),
+        just("*").ignore_then(amount()).map(AutoAmount::Multiplier),
+        amount().map_with(|amount, e| {
+            let state: &mut State = e.state();
+            // in ledger, amounts without a commodity are multipliers
+            if state.dialect == Dialect::Ledger && amount.commodity.is_empty() {
+                AutoAmount::Multiplier(amount)
+            } else {
+                AutoAmount::Amount(amount)
+            }
+        }),
+    ))
 }
 
 pub fn auto_postings<'a>(
 ) -> impl Parser<'a, &'a str, AutosPostingRule, extra::Full<Rich<'a, char>, State, ()>> {
     let header = just("=")
         .ignore_then(whitespace().repeated())
-        .ignore_then(query().then_ignore(end_of_line()))
+        .ignore_then(
+            ledger_query()
+                .or(query().map(Ok))
+                .try_map(|query, span| query.map_err(|error| Rich::custom(span, error)))
+                .then_ignore(end_of_line()),
+        )
         .then_ignore(text::newline());
 
     let account_name = account_name()
         .delimited_by(just('('), just(')'))
-        .map(|name| (name, true))
-        .or(account_name().map(|name| (name, false)));
+        .map(|name| (name, true, false))
+        .or(account_name()
+            .delimited_by(just('['), just(']'))
+            .map(|name| (name, false, true)))
+        .or(account_name().map(|name| (name, false, false)));
     let posting = whitespace()
         .repeated()
         .at_least(1)
         .ignore_then(account_name)
         .then_ignore(whitespace().repeated().at_least(2))
-        .then(just("*").or_not())
-        .then(amount())
+        .then(auto_amount())
         .then_ignore(end_of_line())
-        .map(
-            |(((account_name, is_virtual), is_mul), amount)| AutoPosting {
-                account_name,
-                is_virtual,
-                amount,
-                is_mul: is_mul.is_some(),
+        .map_with(
+            |((account_name, is_virtual, is_balanced_virtual), amount), e| {
+                let state: &mut State = e.state();
+                AutoPosting {
+                    account_name: state.account_name(account_name),
+                    is_virtual,
+                    is_balanced_virtual,
+                    amount,
+                }
             },
         );
 
@@ -64,7 +358,11 @@ pub fn auto_postings<'a>(
                 .at_least(2)
                 .collect::<Vec<_>>(),
         )
-        .map(|(query, postings)| AutosPostingRule { query, postings })
+        .map_with(|(query, postings), e| AutosPostingRule {
+            query,
+            postings,
+            position: e.span().into_range(),
+        })
 }
 
 #[cfg(test)]
@@ -100,12 +398,13 @@ mod tests {
                             String::from("gifts")
                         ]),
                         is_virtual: false,
-                        is_mul: true,
-                        amount: Amount {
+                        is_balanced_virtual: false,
+                        amount: AutoAmount::Multiplier(Amount {
                             quantity: Decimal::new(-1, 0),
                             commodity: String::from("$"),
                             price: None,
-                        },
+                            ..Amount::default()
+                        }),
                     },
                     AutoPosting {
                         account_name: AccountName::from_parts(&[
@@ -113,15 +412,97 @@ mod tests {
                             String::from("checking")
                         ]),
                         is_virtual: true,
-                        is_mul: false,
-                        amount: Amount {
+                        is_balanced_virtual: false,
+                        amount: AutoAmount::Amount(Amount {
                             quantity: Decimal::new(1, 0),
                             commodity: String::new(),
                             price: None,
-                        },
+                            ..Amount::default()
+                        }),
                     }
                 ],
+                position: 0..80,
             })
         );
     }
+
+    fn matched(amount: &str) -> Posting {
+        let directives = crate::parse(format!(
+            "2024-01-01 test\n    expenses:food  {amount}\n    assets"
+        ))
+        .unwrap();
+        let Some(crate::Directive::Transaction(transaction)) = directives.first() else {
+            panic!("expected transaction");
+        };
+        transaction.postings[0].clone()
+    }
+
+    fn evaluate(input: &str, matched_amount: &str) -> Option<Vec<Amount>> {
+        auto_amount()
+            .then_ignore(end())
+            .parse(input)
+            .into_result()
+            .unwrap()
+            .evaluate(&matched(matched_amount).amount)
+    }
+
+    fn amount(quantity: i64, scale: u32, commodity: &str) -> Amount {
+        Amount {
+            quantity: Decimal::new(quantity, scale),
+            commodity: String::from(commodity),
+            ..Amount::default()
+        }
+    }
+
+    #[test]
+    fn evaluate_amount() {
+        assert_eq!(evaluate("$2", "10 EUR"), Some(vec![amount(2, 0, "$")]));
+        assert_eq!(evaluate("2", "10 EUR"), Some(vec![amount(2, 0, "EUR")]));
+    }
+
+    #[test]
+    fn evaluate_multiplier() {
+        assert_eq!(evaluate("*0.5", "10 EUR"), Some(vec![amount(50, 1, "EUR")]));
+        assert_eq!(evaluate("*$2", "10 EUR"), Some(vec![amount(20, 0, "$")]));
+        assert_eq!(
+            evaluate("*-1", "10 EUR @@ $12"),
+            Some(vec![Amount {
+                price: Some(Box::new(AmountPrice::Total(amount(-12, 0, "$")))),
+                ..amount(-10, 0, "EUR")
+            }])
+        );
+    }
+
+    #[test]
+    fn evaluate_expression() {
+        assert_eq!(
+            evaluate("(amount * 0.25 + 1)", "10 EUR"),
+            Some(vec![amount(350, 2, "EUR")])
+        );
+        assert_eq!(
+            evaluate("(-(a - $2) / 2)", "$10"),
+            Some(vec![amount(-4, 0, "$")])
+        );
+        assert_eq!(evaluate("(amount * $2)", "$10"), None);
+        assert_eq!(evaluate("(amount / 0)", "$10"), None);
+    }
+
+    #[test]
+    fn evaluate_rule() {
+        let rule = auto_postings()
+            .then_ignore(end())
+            .parse(
+                "= expenses:food
+    (budget:food)  *-1
+    assets:savings  $1",
+            )
+            .into_result()
+            .unwrap();
+        let postings = rule.evaluate(&matched("$10")).unwrap();
+        assert_eq!(postings.len(), 2);
+        assert!(postings[0].is_virtual);
+        assert!(postings.iter().all(|posting| posting.is_generated));
+        assert_eq!(postings[0].amount, vec![amount(-10, 0, "$")]);
+        assert_eq!(postings[1].account_name.to_string(), "assets:savings");
+    }
 }
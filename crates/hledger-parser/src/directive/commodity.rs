@@ -42,6 +42,7 @@ mod tests {
                 commodity: String::from("$"),
                 quantity: Decimal::new(100_000, 2),
                 price: None,
+                ..Amount::default()
             }))
         );
     }
@@ -58,6 +59,7 @@ mod tests {
                 commodity: String::new(),
                 quantity: Decimal::new(10_000_000_000, 4),
                 price: None,
+                ..Amount::default()
             }))
         );
     }
@@ -74,6 +76,7 @@ mod tests {
                 commodity: String::from("USD"),
                 quantity: Decimal::new(1, 0),
                 price: None,
+                ..Amount::default()
             }))
         );
     }
@@ -0,0 +1,426 @@
+use chumsky::error::Error;
+use chumsky::prelude::*;
+use chumsky::util::MaybeRef;
+
+use crate::component::account_name::account_name;
+use crate::component::amount::amount;
+use crate::component::date::simple::date;
+use crate::component::price::AmountPrice;
+use crate::component::query::{Condition, Term};
+use crate::component::whitespace::whitespace;
+use crate::directive::include::include as hledger_include;
+use crate::directive::unsupported::Unsupported;
+use crate::directive::Directive;
+use crate::options::Dialect;
+use crate::state::State;
+use crate::utils::end_of_line;
+
+/// Succeeds without consuming input only when parsing the ledger dialect.
+///
+/// Otherwise it fails without expecting anything, so that hledger syntax errors are reported as
+/// before.
+pub fn ledger_dialect<'a>() -> impl Parser<'a, &'a str, (), extra::Full<Rich<'a, char>, State, ()>>
+{
+    any()
+        .map(Some)
+        .or(end().map(|()| None))
+        .rewind()
+        .try_map_with(|found, e| {
+            let state: &mut State = e.state();
+            if state.dialect == Dialect::Ledger {
+                Ok(())
+            } else {
+                Err(<Rich<char> as Error<&str>>::expected_found(
+                    std::iter::empty::<Option<MaybeRef<char>>>(),
+                    found.map(MaybeRef::Val),
+                    e.span(),
+                ))
+            }
+        })
+}
+
+pub fn directive<'a>(
+) -> impl Parser<'a, &'a str, Option<Directive>, extra::Full<Rich<'a, char>, State, ()>> {
+    ledger_dialect().ignore_then(choice((
+        include().map(|include| Some(Directive::Include(include))),
+        bucket().map(|()| None),
+        end_apply_tag().map(|unsupported| Some(Directive::Unsupported(unsupported))),
+        apply_tag().map(|unsupported| Some(Directive::Unsupported(unsupported))),
+        conversion().map(|unsupported| Some(Directive::Unsupported(unsupported))),
+    )))
+}
+
+// !include path
+fn include<'a>(
+) -> impl Parser<'a, &'a str, crate::directive::Include, extra::Full<Rich<'a, char>, State, ()>> {
+    just("!").ignore_then(hledger_include())
+}
+
+// bucket account, or A account: balancing account of transactions with a single posting
+fn bucket<'a>() -> impl Parser<'a, &'a str, (), extra::Full<Rich<'a, char>, State, ()>> {
+    just("bucket")
+        .or(just("A"))
+        .ignore_then(whitespace().repeated().at_least(1))
+        .ignore_then(account_name())
+        .then_ignore(end_of_line())
+        .map_with(|account_name, e| {
+            let state: &mut State = e.state();
+            state.bucket = Some(account_name);
+        })
+}
+
+// apply tag name
+fn apply_tag<'a>() -> impl Parser<'a, &'a str, Unsupported, extra::Full<Rich<'a, char>, State, ()>>
+{
+    just("apply")
+        .then(whitespace().repeated().at_least(1))
+        .then(just("tag"))
+        .then(whitespace().repeated().at_least(1))
+        .then(rest_of_line())
+        .map_with(|_, e| Unsupported {
+            directive: String::from("apply tag"),
+            position: e.span().into_range(),
+        })
+}
+
+// end apply tag
+fn end_apply_tag<'a>(
+) -> impl Parser<'a, &'a str, Unsupported, extra::Full<Rich<'a, char>, State, ()>> {
+    just("end")
+        .then(whitespace().repeated().at_least(1))
+        .then(just("apply"))
+        .then(whitespace().repeated().at_least(1))
+        .then(just("tag"))
+        .then_ignore(end_of_line())
+        .map_with(|_, e| Unsupported {
+            directive: String::from("end apply tag"),
+            position: e.span().into_range(),
+        })
+}
+
+// C 1.00 Kb = 1024 bytes
+fn conversion<'a>() -> impl Parser<'a, &'a str, Unsupported, extra::Full<Rich<'a, char>, State, ()>>
+{
+    just("C")
+        .then(whitespace().repeated().at_least(1))
+        .then(amount())
+        .then(whitespace().repeated())
+        .then(just("="))
+        .then(whitespace().repeated())
+        .then(amount())
+        .then_ignore(end_of_line())
+        .map_with(|_, e| Unsupported {
+            directive: String::from("C"),
+            position: e.span().into_range(),
+        })
+}
+
+fn rest_of_line<'a>() -> impl Parser<'a, &'a str, String, extra::Full<Rich<'a, char>, State, ()>> {
+    any()
+        .and_is(text::newline().not())
+        .repeated()
+        .at_least(1)
+        .collect::<String>()
+}
+
+/// Lot annotation of an amount: `{$50}`, `{{$500}}`, `[2012-04-10]` or `(note)`. Only the lot
+/// price is kept, it is used as a cost when the posting does not have one.
+pub fn lot<'a>(
+) -> impl Parser<'a, &'a str, Option<AmountPrice>, extra::Full<Rich<'a, char>, State, ()>> {
+    let braced = |open: &'static str, close: &'static str| {
+        just(open)
+            .ignore_then(whitespace().repeated())
+            .ignore_then(just("=").or_not())
+            .ignore_then(whitespace().repeated())
+            .ignore_then(amount())
+            .then_ignore(whitespace().repeated())
+            .then_ignore(just(close))
+    };
+    let price = braced("{{", "}}")
+        .map(AmountPrice::Total)
+        .or(braced("{", "}").map(AmountPrice::Unit));
+    let lot_date = date().delimited_by(just("["), just("]"));
+    let note = any()
+        .and_is(text::newline().not())
+        .and_is(just(")").not())
+        .repeated()
+        .delimited_by(just("("), just(")"));
+    ledger_dialect().ignore_then(
+        whitespace()
+            .repeated()
+            .ignore_then(price)
+            .or_not()
+            .then_ignore(whitespace().repeated().ignore_then(lot_date).or_not())
+            .then_ignore(whitespace().repeated().ignore_then(note).or_not()),
+    )
+}
+
+/// Query of a ledger automated transaction: `/regex/` or `expr 'account =~ /regex/'`.
+///
+/// Unsupported value expressions are returned as errors instead of failing, so that they are not
+/// parsed as hledger queries.
+pub fn query<'a>(
+) -> impl Parser<'a, &'a str, Result<Vec<Term>, String>, extra::Full<Rich<'a, char>, State, ()>> {
+    let regex = any()
+        .and_is(just("/").not())
+        .and_is(text::newline().not())
+        .repeated()
+        .at_least(1)
+        .collect::<String>()
+        .delimited_by(just("/"), just("/"));
+    let account_regex = regex.map(|regex| {
+        Ok(vec![Term {
+            condition: Condition::Account(regex),
+            is_not: false,
+        }])
+    });
+    let quoted = any()
+        .and_is(just("'").not())
+        .and_is(text::newline().not())
+        .repeated()
+        .collect::<String>()
+        .delimited_by(just("'"), just("'"));
+    let unquoted = any()
+        .and_is(just(";").not())
+        .and_is(text::newline().not())
+        .repeated()
+        .at_least(1)
+        .collect::<String>();
+    let expr = just("expr")
+        .ignore_then(whitespace().repeated().at_least(1))
+        .ignore_then(quoted.or(unquoted))
+        .map(|expression| {
+            let expression = expression.trim();
+            value_expression(expression)
+                .ok_or(format!("unsupported value expression: {expression}"))
+        });
+    ledger_dialect().ignore_then(account_regex.or(expr))
+}
+
+// only simple matches of a single field are supported: account =~ /regex/
+fn value_expression(expression: &str) -> Option<Vec<Term>> {
+    let (field, regex) = expression.split_once("=~")?;
+    let regex = regex
+        .trim()
+        .strip_prefix('/')?
+        .strip_suffix('/')?
+        .to_string();
+    let condition = match field.trim() {
+        "account" => Condition::Account(regex),
+        "payee" => Condition::Payee(regex),
+        "note" => Condition::Note(regex),
+        "commodity" => Condition::Currency(regex),
+        _ => return None,
+    };
+    Some(vec![Term {
+        condition,
+        is_not: false,
+    }])
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal::Decimal;
+
+    use super::*;
+    use crate::component::amount::Amount;
+    use crate::ParseOptions;
+
+    fn ledger() -> ParseOptions {
+        ParseOptions::default().with_dialect(Dialect::Ledger)
+    }
+
+    #[test]
+    fn include() {
+        let result = crate::parse_with_options("!include other.ledger", &ledger()).unwrap();
+        assert_eq!(
+            result,
+            vec![Directive::Include(crate::Include {
+                format: None,
+                path: std::path::PathBuf::from("other.ledger"),
+            })]
+        );
+    }
+
+    #[test]
+    fn include_requires_dialect() {
+        let result = crate::parse("!include other.ledger");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn bucket() {
+        let result = crate::parse_with_options(
+            "bucket Assets:Checking
+2024-01-01 Grocery
+    Expenses:Food  $10",
+            &ledger(),
+        )
+        .unwrap();
+        let Some(Directive::Transaction(transaction)) = result.first() else {
+            panic!("expected transaction");
+        };
+        assert_eq!(transaction.postings.len(), 2);
+        assert_eq!(
+            transaction.postings[1].account_name.to_string(),
+            "Assets:Checking"
+        );
+        assert!(!transaction.postings[1].is_amount_specified);
+    }
+
+    #[test]
+    fn unsupported() {
+        let result = crate::parse_with_options(
+            "apply tag hastag
+end apply tag
+C 1.00 Kb = 1024 bytes",
+            &ledger(),
+        )
+        .unwrap();
+        assert_eq!(
+            result,
+            vec![
+                Directive::Unsupported(Unsupported {
+                    directive: String::from("apply tag"),
+                    position: 0..16,
+                }),
+                Directive::Unsupported(Unsupported {
+                    directive: String::from("end apply tag"),
+                    position: 17..30,
+                }),
+                Directive::Unsupported(Unsupported {
+                    directive: String::from("C"),
+                    position: 31..53,
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn lot_price() {
+        let result = crate::parse_with_options(
+            "2024-01-01 Buy
+    Assets:Brokerage  10 AAPL {$50.00} [2024-01-01] (lot)
+    Assets:Checking",
+            &ledger(),
+        )
+        .unwrap();
+        let Some(Directive::Transaction(transaction)) = result.first() else {
+            panic!("expected transaction");
+        };
+        assert_eq!(
+            transaction.postings[0].amount[0].price,
+            Some(Box::new(AmountPrice::Unit(Amount {
+                quantity: Decimal::new(5000, 2),
+                commodity: String::from("$"),
+                price: None,
+                ..Amount::default()
+            })))
+        );
+    }
+
+    #[test]
+    fn fixated_price() {
+        let result = crate::parse_with_options(
+            "2024-01-01 Buy
+    Assets:Brokerage  10 AAPL @@@ $500
+    Assets:Checking",
+            &ledger(),
+        )
+        .unwrap();
+        let Some(Directive::Transaction(transaction)) = result.first() else {
+            panic!("expected transaction");
+        };
+        assert_eq!(
+            transaction.postings[0].amount[0].price,
+            Some(Box::new(AmountPrice::Total(Amount {
+                quantity: Decimal::new(500, 0),
+                commodity: String::from("$"),
+                ..Amount::default()
+            })))
+        );
+
+        let result = crate::parse(
+            "2024-01-01 Buy
+    Assets:Brokerage  10 AAPL @@@ $500
+    Assets:Checking",
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn payee_separator() {
+        let result =
+            crate::parse_with_options("2024-01-01 Grocery -- weekly shopping", &ledger()).unwrap();
+        let Some(Directive::Transaction(transaction)) = result.first() else {
+            panic!("expected transaction");
+        };
+        assert_eq!(transaction.payee, "Grocery");
+        assert_eq!(transaction.note, Some(String::from("weekly shopping")));
+    }
+
+    #[test]
+    fn automated_transaction() {
+        let result = crate::parse_with_options(
+            "= expr 'account =~ /^Expenses:Food/'
+    (Budget:Food)  -1
+    (Budget:Total)  1",
+            &ledger(),
+        )
+        .unwrap();
+        let Some(Directive::AutoPostings(rule)) = result.first() else {
+            panic!("expected auto postings");
+        };
+        assert_eq!(
+            rule.query,
+            vec![Term {
+                condition: Condition::Account(String::from("^Expenses:Food")),
+                is_not: false,
+            }]
+        );
+        assert!(matches!(
+            rule.postings[0].amount,
+            crate::AutoAmount::Multiplier(_)
+        ));
+    }
+
+    #[test]
+    fn automated_transaction_regex() {
+        let result = crate::parse_with_options(
+            "= /^Income/
+    (Liabilities:Tax)  0.33
+    (Assets:Savings)  $10",
+            &ledger(),
+        )
+        .unwrap();
+        let Some(Directive::AutoPostings(rule)) = result.first() else {
+            panic!("expected auto postings");
+        };
+        assert_eq!(
+            rule.query,
+            vec![Term {
+                condition: Condition::Account(String::from("^Income")),
+                is_not: false,
+            }]
+        );
+        assert!(matches!(
+            rule.postings[0].amount,
+            crate::AutoAmount::Multiplier(_)
+        ));
+        assert!(matches!(
+            rule.postings[1].amount,
+            crate::AutoAmount::Amount(_)
+        ));
+    }
+
+    #[test]
+    fn unsupported_value_expression() {
+        let result = crate::parse_with_options(
+            "= expr 'amount > 100'
+    (Budget:Food)  -1
+    (Budget:Total)  1",
+            &ledger(),
+        );
+        assert!(result.is_err());
+    }
+}
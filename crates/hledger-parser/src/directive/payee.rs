@@ -1,12 +1,16 @@
+use std::ops::Range;
+
 use chumsky::prelude::*;
 
+use crate::component::metadata::{metadata, Metadata};
 use crate::component::whitespace::whitespace;
 use crate::state::State;
-use crate::utils::end_of_line;
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Payee {
     pub name: String,
+    pub metadata: Metadata,
+    pub position: Range<usize>,
 }
 
 pub fn payee<'a>() -> impl Parser<'a, &'a str, Payee, extra::Full<Rich<'a, char>, State, ()>> {
@@ -20,9 +24,11 @@ pub fn payee<'a>() -> impl Parser<'a, &'a str, Payee, extra::Full<Rich<'a, char>
                 .at_least(1)
                 .collect::<String>(),
         )
-        .then_ignore(end_of_line())
-        .map(|payee| Payee {
+        .then(metadata())
+        .map_with(|(payee, metadata), e| Payee {
             name: payee.trim_end().to_string(),
+            metadata,
+            position: e.span().into_range(),
         })
 }
 
@@ -36,7 +42,9 @@ mod tests {
         assert_eq!(
             result,
             Ok(Payee {
-                name: String::from("Test")
+                name: String::from("Test"),
+                metadata: Metadata::default(),
+                position: 0..10,
             })
         );
     }
@@ -50,7 +58,12 @@ mod tests {
         assert_eq!(
             result,
             Ok(Payee {
-                name: String::from("Test")
+                name: String::from("Test"),
+                metadata: Metadata {
+                    comment: Some(String::from("comment")),
+                    ..Metadata::default()
+                },
+                position: 0..20,
             })
         );
     }
@@ -64,7 +77,9 @@ mod tests {
         assert_eq!(
             result,
             Ok(Payee {
-                name: String::from("Testing things")
+                name: String::from("Testing things"),
+                metadata: Metadata::default(),
+                position: 0..20,
             })
         );
     }
@@ -78,7 +93,32 @@ mod tests {
         assert_eq!(
             result,
             Ok(Payee {
-                name: String::from("123")
+                name: String::from("123"),
+                metadata: Metadata::default(),
+                position: 0..11,
+            })
+        );
+    }
+
+    #[test]
+    fn ok_with_metadata() {
+        let result = payee()
+            .then_ignore(end())
+            .parse("payee Whole Foods  ; category: groceries\n    ; online: yes\n    alias WF")
+            .into_result();
+        assert_eq!(
+            result,
+            Ok(Payee {
+                name: String::from("Whole Foods"),
+                metadata: Metadata {
+                    comment: Some(String::from("category: groceries\nonline: yes")),
+                    tags: vec![
+                        (String::from("category"), String::from("groceries")),
+                        (String::from("online"), String::from("yes")),
+                    ],
+                    subdirectives: vec![String::from("alias WF")],
+                },
+                position: 0..71,
             })
         );
     }
@@ -53,6 +53,7 @@ mod tests {
                     quantity: Decimal::new(135, 2),
                     commodity: String::from("$"),
                     price: None,
+                    ..Amount::default()
                 },
             })
         );
@@ -73,6 +74,7 @@ mod tests {
                     quantity: Decimal::new(69_174_770_790_400, 8),
                     commodity: String::from("SEK"),
                     price: None,
+                    ..Amount::default()
                 },
             })
         );
@@ -93,6 +95,7 @@ mod tests {
                     quantity: Decimal::new(135, 2),
                     commodity: String::from("$"),
                     price: None,
+                    ..Amount::default()
                 },
             })
         );
@@ -1,12 +1,16 @@
+use std::ops::Range;
+
 use chumsky::prelude::*;
 
+use crate::component::metadata::{metadata, Metadata};
 use crate::component::whitespace::whitespace;
 use crate::state::State;
-use crate::utils::end_of_line;
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Tag {
     pub name: String,
+    pub metadata: Metadata,
+    pub position: Range<usize>,
 }
 
 pub fn tag<'a>() -> impl Parser<'a, &'a str, Tag, extra::Full<Rich<'a, char>, State, ()>> {
@@ -21,9 +25,11 @@ pub fn tag<'a>() -> impl Parser<'a, &'a str, Tag, extra::Full<Rich<'a, char>, St
                 .at_least(1)
                 .collect::<String>(),
         )
-        .then_ignore(end_of_line())
-        .map(|tag| Tag {
+        .then(metadata())
+        .map_with(|(tag, metadata), e| Tag {
             name: tag.trim_end().to_string(),
+            metadata,
+            position: e.span().into_range(),
         })
 }
 
@@ -37,7 +43,9 @@ mod tests {
         assert_eq!(
             result,
             Ok(Tag {
-                name: String::from("test-tag")
+                name: String::from("test-tag"),
+                metadata: Metadata::default(),
+                position: 0..12,
             })
         );
     }
@@ -51,7 +59,12 @@ mod tests {
         assert_eq!(
             result,
             Ok(Tag {
-                name: String::from("Test")
+                name: String::from("Test"),
+                metadata: Metadata {
+                    comment: Some(String::from("comment")),
+                    ..Metadata::default()
+                },
+                position: 0..18,
             })
         );
     }
@@ -71,7 +84,29 @@ mod tests {
         assert_eq!(
             result,
             Ok(Tag {
-                name: String::from("123")
+                name: String::from("123"),
+                metadata: Metadata::default(),
+                position: 0..9,
+            })
+        );
+    }
+
+    #[test]
+    fn ok_with_tags() {
+        let result = tag()
+            .then_ignore(end())
+            .parse("tag trip\n  ; kind: travel")
+            .into_result();
+        assert_eq!(
+            result,
+            Ok(Tag {
+                name: String::from("trip"),
+                metadata: Metadata {
+                    comment: Some(String::from("kind: travel")),
+                    tags: vec![(String::from("kind"), String::from("travel"))],
+                    subdirectives: Vec::new(),
+                },
+                position: 0..25,
             })
         );
     }
@@ -1,7 +1,9 @@
 use chumsky::prelude::*;
 
+use crate::component::comment::Comment;
 use crate::component::status::{status, Status};
 use crate::component::whitespace::whitespace;
+use crate::options::Dialect;
 use crate::state::State;
 use crate::utils::end_of_line;
 
@@ -11,6 +13,8 @@ pub struct Header {
     pub code: Option<String>,
     pub payee: String,
     pub note: Option<String>,
+    pub comment: Option<String>,
+    pub tags: Vec<(String, String)>,
 }
 
 pub fn header<'a>() -> impl Parser<'a, &'a str, Header, extra::Full<Rich<'a, char>, State, ()>> {
@@ -42,11 +46,33 @@ pub fn header<'a>() -> impl Parser<'a, &'a str, Header, extra::Full<Rich<'a, cha
         .then(whitespace().repeated().ignore_then(code).or_not())
         .then(whitespace().repeated().ignore_then(payee))
         .then(whitespace().repeated().ignore_then(note).or_not())
-        .then_ignore(end_of_line())
-        .map(|(((status, code), payee), note)| Header {
-            status,
-            code,
-            payee: payee.trim().to_string(),
-            note,
+        .then(end_of_line())
+        .map_with(|((((status, code), payee), note), comment), e| {
+            let tags = comment.as_ref().map(Comment::tags).unwrap_or_default();
+            let comment = comment
+                .map(|comment| comment.text().to_string())
+                .filter(|comment| !comment.is_empty());
+            let state: &mut State = e.state();
+            // ledger separates the note from the payee with two dashes
+            if state.dialect == Dialect::Ledger && note.is_none() {
+                if let Some((payee, note)) = payee.split_once(" -- ") {
+                    return Header {
+                        status,
+                        code,
+                        payee: payee.trim().to_string(),
+                        note: Some(note.trim().to_string()),
+                        comment,
+                        tags,
+                    };
+                }
+            }
+            Header {
+                status,
+                code,
+                payee: payee.trim().to_string(),
+                note,
+                comment,
+                tags,
+            }
         })
 }
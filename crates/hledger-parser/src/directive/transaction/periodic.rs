@@ -17,6 +17,7 @@ pub struct Transaction {
     pub payee: String,
     pub note: Option<String>,
     pub postings: Vec<Posting>,
+    pub position: std::ops::Range<usize>,
 }
 
 pub fn transaction<'a>(
@@ -48,7 +49,7 @@ pub fn transaction<'a>(
                 .allow_leading()
                 .collect::<Vec<_>>(),
         )
-        .map(|(((interval, period), header), postings)| Transaction {
+        .map_with(|(((interval, period), header), postings), e| Transaction {
             period,
             interval,
             status: header.as_ref().and_then(|h| h.status.clone()),
@@ -56,6 +57,7 @@ pub fn transaction<'a>(
             payee: header.as_ref().map_or(String::new(), |h| h.payee.clone()),
             note: header.as_ref().and_then(|h| h.note.clone()),
             postings,
+            position: e.span().into_range(),
         })
 }
 
@@ -100,10 +102,16 @@ mod tests {
                             quantity: Decimal::new(400, 0),
                             commodity: String::from("$"),
                             price: None,
+                            ..Amount::default()
                         }],
                         is_amount_specified: true,
                         assertion: None,
                         is_virtual: false,
+                        is_balanced_virtual: false,
+                        is_generated: false,
+                        comment: None,
+                        tags: Vec::new(),
+                        position: 53..89,
                     },
                     Posting {
                         status: None,
@@ -116,8 +124,14 @@ mod tests {
                         is_amount_specified: false,
                         assertion: None,
                         is_virtual: false,
+                        is_balanced_virtual: false,
+                        is_generated: false,
+                        comment: None,
+                        tags: Vec::new(),
+                        position: 90..114,
                     }
                 ],
+                position: 0..114,
             })
         );
     }
@@ -152,10 +166,16 @@ mod tests {
                             quantity: Decimal::new(400, 0),
                             commodity: String::from("$"),
                             price: None,
+                            ..Amount::default()
                         }],
                         is_amount_specified: true,
                         assertion: None,
                         is_virtual: false,
+                        is_balanced_virtual: false,
+                        is_generated: false,
+                        comment: None,
+                        tags: Vec::new(),
+                        position: 23..59,
                     },
                     Posting {
                         status: None,
@@ -168,8 +188,14 @@ mod tests {
                         is_amount_specified: false,
                         assertion: None,
                         is_virtual: false,
+                        is_balanced_virtual: false,
+                        is_generated: false,
+                        comment: None,
+                        tags: Vec::new(),
+                        position: 60..84,
                     }
                 ],
+                position: 0..84,
             })
         );
     }
@@ -207,10 +233,16 @@ mod tests {
                             quantity: Decimal::new(400, 0),
                             commodity: String::from("$"),
                             price: None,
+                            ..Amount::default()
                         }],
                         is_amount_specified: true,
                         assertion: None,
                         is_virtual: false,
+                        is_balanced_virtual: false,
+                        is_generated: false,
+                        comment: None,
+                        tags: Vec::new(),
+                        position: 45..81,
                     },
                     Posting {
                         status: None,
@@ -223,8 +255,14 @@ mod tests {
                         is_amount_specified: false,
                         assertion: None,
                         is_virtual: false,
+                        is_balanced_virtual: false,
+                        is_generated: false,
+                        comment: None,
+                        tags: Vec::new(),
+                        position: 82..106,
                     }
                 ],
+                position: 0..106,
             })
         );
     }
@@ -259,10 +297,16 @@ mod tests {
                             quantity: Decimal::new(1000, 0),
                             commodity: String::from("$"),
                             price: None,
+                            ..Amount::default()
                         }],
                         is_amount_specified: true,
                         assertion: None,
                         is_virtual: true,
+                        is_balanced_virtual: false,
+                        is_generated: false,
+                        comment: None,
+                        tags: Vec::new(),
+                        position: 74..104,
                     },
                     Posting {
                         status: None,
@@ -274,12 +318,19 @@ mod tests {
                             quantity: Decimal::new(500, 0),
                             commodity: String::from("$"),
                             price: None,
+                            ..Amount::default()
                         }],
                         is_amount_specified: true,
                         assertion: None,
                         is_virtual: true,
+                        is_balanced_virtual: false,
+                        is_generated: false,
+                        comment: None,
+                        tags: Vec::new(),
+                        position: 105..135,
                     }
                 ],
+                position: 0..135,
             })
         );
     }
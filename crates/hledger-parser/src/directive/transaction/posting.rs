@@ -4,9 +4,11 @@ mod assertion;
 
 use crate::component::account_name::{account_name, AccountName};
 use crate::component::amount::{amount, Amount};
-use crate::component::price::amount_price;
+use crate::component::comment::Comment;
+use crate::component::price::{amount_price, AmountPrice};
 use crate::component::status::{status, Status};
 use crate::component::whitespace::whitespace;
+use crate::directive::ledger::{ledger_dialect, lot};
 use crate::directive::transaction::posting::assertion::assertion;
 use crate::state::State;
 use crate::utils::end_of_line;
@@ -14,24 +16,52 @@ use crate::utils::end_of_line;
 pub use crate::directive::transaction::posting::assertion::Assertion;
 
 #[derive(Debug, Clone, Hash, PartialEq)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct Posting {
     pub status: Option<Status>,
     pub account_name: AccountName,
     pub is_virtual: bool,
+    /// Posting to a `[bracketed]` account, which is virtual but must balance like real postings.
+    pub is_balanced_virtual: bool,
     pub amount: Vec<Amount>,
     pub is_amount_specified: bool,
     pub assertion: Option<Assertion>,
+    /// Whether the posting was generated by an auto posting rule rather than written in the
+    /// journal.
+    pub is_generated: bool,
+    /// Text of the posting comment, its lines without their `;`.
+    pub comment: Option<String>,
+    /// `name: value` tags of the posting comment.
+    pub tags: Vec<(String, String)>,
+    /// Range of the posting line in the text of its file. Postings that are not written in the
+    /// journal have the range of what they were generated from.
+    pub position: std::ops::Range<usize>,
 }
 
 #[must_use]
 pub fn posting<'a>() -> impl Parser<'a, &'a str, Posting, extra::Full<Rich<'a, char>, State, ()>> {
-    let posting_amount = whitespace().repeated().at_least(2).ignore_then(amount());
-    let posting_price = whitespace().repeated().ignore_then(amount_price());
+    let posting_amount = whitespace()
+        .repeated()
+        .at_least(2)
+        .ignore_then(amount())
+        .then(lot().or_not().map(Option::flatten));
+    // ledger's fixated total price, which only differs from `@@` in how lots are valued
+    let fixated_price = ledger_dialect()
+        .ignore_then(just("@@@"))
+        .ignore_then(whitespace().repeated())
+        .ignore_then(amount())
+        .map(AmountPrice::Total);
+    let posting_price = whitespace()
+        .repeated()
+        .ignore_then(fixated_price.or(amount_price()));
     let posting_assertion = whitespace().repeated().ignore_then(assertion());
     let account_name = account_name()
         .delimited_by(just('('), just(')'))
-        .map(|name| (name, true))
-        .or(account_name().map(|name| (name, false)));
+        .map(|name| (name, true, false))
+        .or(account_name()
+            .delimited_by(just('['), just(']'))
+            .map(|name| (name, false, true)))
+        .or(account_name().map(|name| (name, false, false)));
     whitespace()
         .repeated()
         .at_least(1)
@@ -40,21 +70,39 @@ pub fn posting<'a>() -> impl Parser<'a, &'a str, Posting, extra::Full<Rich<'a, c
         .then(posting_amount.or_not())
         .then(posting_price.or_not())
         .then(posting_assertion.or_not())
-        .then_ignore(end_of_line())
-        .map(
-            |((((status, (account_name, is_virtual)), amount), price), assertion)| Posting {
-                status,
-                account_name,
-                is_virtual,
-                is_amount_specified: amount.is_some(),
-                amount: amount
-                    .map(|amount| Amount {
-                        price: price.map(Box::new),
-                        ..amount
-                    })
-                    .map(|amount| vec![amount])
-                    .unwrap_or_default(),
-                assertion,
+        .then(end_of_line())
+        .map_with(
+            |(
+                (
+                    (((status, (account_name, is_virtual, is_balanced_virtual)), amount), price),
+                    assertion,
+                ),
+                comment,
+            ),
+             e| {
+                let position = e.span().into_range();
+                let state: &mut State = e.state();
+                Posting {
+                    status,
+                    account_name: state.account_name(account_name),
+                    is_virtual,
+                    is_balanced_virtual,
+                    is_amount_specified: amount.is_some(),
+                    amount: amount
+                        .map(|(amount, lot_price)| Amount {
+                            price: price.or(lot_price).map(Box::new),
+                            ..amount
+                        })
+                        .map(|amount| vec![amount])
+                        .unwrap_or_default(),
+                    assertion,
+                    is_generated: false,
+                    tags: comment.as_ref().map(Comment::tags).unwrap_or_default(),
+                    comment: comment
+                        .map(|comment| comment.text().to_string())
+                        .filter(|comment| !comment.is_empty()),
+                    position,
+                }
             },
         )
 }
@@ -86,10 +134,16 @@ mod tests {
                     quantity: Decimal::new(1, 0),
                     commodity: String::from("$"),
                     price: None,
+                    ..Amount::default()
                 }],
                 is_amount_specified: true,
                 assertion: None,
                 is_virtual: false,
+                is_balanced_virtual: false,
+                is_generated: false,
+                comment: None,
+                tags: Vec::new(),
+                position: 0..28,
             })
         );
     }
@@ -113,6 +167,11 @@ mod tests {
                 is_amount_specified: false,
                 assertion: None,
                 is_virtual: false,
+                is_balanced_virtual: false,
+                is_generated: false,
+                comment: None,
+                tags: Vec::new(),
+                position: 0..23,
             })
         );
     }
@@ -136,10 +195,16 @@ mod tests {
                     quantity: Decimal::new(1, 0),
                     commodity: String::from("$"),
                     price: None,
+                    ..Amount::default()
                 }],
                 is_amount_specified: true,
                 assertion: None,
                 is_virtual: false,
+                is_balanced_virtual: false,
+                is_generated: false,
+                comment: None,
+                tags: Vec::new(),
+                position: 0..26,
             })
         );
     }
@@ -166,6 +231,13 @@ mod tests {
                 is_amount_specified: false,
                 assertion: None,
                 is_virtual: false,
+                is_balanced_virtual: false,
+                is_generated: false,
+                comment: Some(String::from(
+                    " some comment\n continuation of the same comment"
+                )),
+                tags: Vec::new(),
+                position: 0..108,
             })
         );
     }
@@ -189,6 +261,11 @@ mod tests {
                 is_amount_specified: false,
                 assertion: None,
                 is_virtual: false,
+                is_balanced_virtual: false,
+                is_generated: false,
+                comment: None,
+                tags: Vec::new(),
+                position: 0..21,
             })
         );
     }
@@ -215,7 +292,9 @@ mod tests {
                         quantity: Decimal::new(1, 0),
                         commodity: String::from("USD"),
                         price: None,
+                        ..Amount::default()
                     }))),
+                    ..Amount::default()
                 }],
                 is_amount_specified: true,
                 assertion: Some(Assertion {
@@ -223,11 +302,17 @@ mod tests {
                         quantity: Decimal::new(1, 0),
                         commodity: String::from("USD"),
                         price: None,
+                        ..Amount::default()
                     },
                     is_subaccount_inclusive: false,
                     is_strict: false,
                 }),
                 is_virtual: false,
+                is_balanced_virtual: false,
+                is_generated: false,
+                comment: None,
+                tags: Vec::new(),
+                position: 0..41,
             })
         );
     }
@@ -251,6 +336,7 @@ mod tests {
                     quantity: Decimal::new(1, 0),
                     commodity: String::from("USD"),
                     price: None,
+                    ..Amount::default()
                 }],
                 is_amount_specified: true,
                 assertion: Some(Assertion {
@@ -258,11 +344,17 @@ mod tests {
                         quantity: Decimal::new(1, 0),
                         commodity: String::from("USD"),
                         price: None,
+                        ..Amount::default()
                     },
                     is_subaccount_inclusive: false,
                     is_strict: true,
                 }),
                 is_virtual: false,
+                is_balanced_virtual: false,
+                is_generated: false,
+                comment: None,
+                tags: Vec::new(),
+                position: 0..37,
             })
         );
     }
@@ -289,12 +381,19 @@ mod tests {
                         quantity: Decimal::new(1, 0),
                         commodity: String::from("EUR"),
                         price: None,
+                        ..Amount::default()
                     }))),
+                    ..Amount::default()
                 }],
                 is_amount_specified: true,
 
                 assertion: None,
                 is_virtual: false,
+                is_balanced_virtual: false,
+                is_generated: false,
+                comment: None,
+                tags: Vec::new(),
+                position: 0..36,
             })
         );
     }
@@ -318,10 +417,48 @@ mod tests {
                     quantity: Decimal::new(1, 0),
                     commodity: String::from("$"),
                     price: None,
+                    ..Amount::default()
                 }],
                 is_amount_specified: true,
                 assertion: None,
                 is_virtual: true,
+                is_balanced_virtual: false,
+                is_generated: false,
+                comment: None,
+                tags: Vec::new(),
+                position: 0..27,
+            })
+        );
+    }
+
+    #[test]
+    fn balanced_virtual_posting() {
+        let result = posting()
+            .then_ignore(end())
+            .parse(" [assets:budget]  $1")
+            .into_result();
+        assert_eq!(
+            result,
+            Ok(Posting {
+                status: None,
+                account_name: AccountName::from_parts(&[
+                    String::from("assets"),
+                    String::from("budget"),
+                ]),
+                amount: vec![Amount {
+                    quantity: Decimal::new(1, 0),
+                    commodity: String::from("$"),
+                    price: None,
+                    ..Amount::default()
+                }],
+                is_amount_specified: true,
+                assertion: None,
+                is_virtual: false,
+                is_balanced_virtual: true,
+                is_generated: false,
+                comment: None,
+                tags: Vec::new(),
+                position: 0..20,
             })
         );
     }
@@ -345,6 +482,11 @@ mod tests {
                 is_amount_specified: false,
                 assertion: None,
                 is_virtual: false,
+                is_balanced_virtual: false,
+                is_generated: false,
+                comment: None,
+                tags: Vec::new(),
+                position: 0..24,
             })
         );
     }
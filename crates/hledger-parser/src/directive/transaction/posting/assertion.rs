@@ -48,6 +48,7 @@ mod tests {
                     commodity: String::from("$"),
                     quantity: Decimal::new(1, 0),
                     price: None,
+                    ..Amount::default()
                 },
             })
         );
@@ -65,6 +66,7 @@ mod tests {
                     commodity: String::from("$"),
                     quantity: Decimal::new(1, 0),
                     price: None,
+                    ..Amount::default()
                 },
             })
         );
@@ -82,6 +84,7 @@ mod tests {
                     commodity: String::from("$"),
                     quantity: Decimal::new(1, 0),
                     price: None,
+                    ..Amount::default()
                 },
             })
         );
@@ -99,6 +102,7 @@ mod tests {
                     commodity: String::from("$"),
                     quantity: Decimal::new(1, 0),
                     price: None,
+                    ..Amount::default()
                 },
             })
         );
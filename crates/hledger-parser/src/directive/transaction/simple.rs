@@ -16,6 +16,13 @@ pub struct Transaction {
     pub note: Option<String>,
     pub postings: Vec<Posting>,
     pub position: std::ops::Range<usize>,
+    /// Whether the transaction was generated, like forecast transactions, rather than written in
+    /// the journal.
+    pub is_generated: bool,
+    /// Text of the transaction comment, its lines without their `;`.
+    pub comment: Option<String>,
+    /// `name: value` tags of the transaction comment.
+    pub tags: Vec<(String, String)>,
 }
 
 pub fn transaction<'a>(
@@ -31,14 +38,36 @@ pub fn transaction<'a>(
                 .allow_leading()
                 .collect::<Vec<_>>(),
         )
-        .map_with(|((date, header), postings), e| Transaction {
-            date,
-            status: header.as_ref().and_then(|h| h.status.clone()),
-            code: header.as_ref().and_then(|h| h.code.clone()),
-            payee: header.as_ref().map_or(String::new(), |h| h.payee.clone()),
-            note: header.as_ref().and_then(|h| h.note.clone()),
-            postings,
-            position: e.span().into_range(),
+        .map_with(|((date, header), mut postings), e| {
+            let position = e.span().into_range();
+            let state: &mut State = e.state();
+            if let (Some(bucket), [_]) = (&state.bucket, postings.as_slice()) {
+                postings.push(Posting {
+                    status: None,
+                    account_name: bucket.clone(),
+                    is_virtual: false,
+                    is_balanced_virtual: false,
+                    is_generated: false,
+                    comment: None,
+                    tags: Vec::new(),
+                    amount: Vec::new(),
+                    is_amount_specified: false,
+                    assertion: None,
+                    position: position.clone(),
+                });
+            }
+            Transaction {
+                date,
+                status: header.as_ref().and_then(|h| h.status.clone()),
+                code: header.as_ref().and_then(|h| h.code.clone()),
+                payee: header.as_ref().map_or(String::new(), |h| h.payee.clone()),
+                note: header.as_ref().and_then(|h| h.note.clone()),
+                postings,
+                position,
+                is_generated: false,
+                comment: header.as_ref().and_then(|h| h.comment.clone()),
+                tags: header.map(|h| h.tags).unwrap_or_default(),
+            }
         })
 }
 
@@ -82,10 +111,16 @@ mod tests {
                             quantity: Decimal::new(1, 0),
                             commodity: String::from("$"),
                             price: None,
+                            ..Amount::default()
                         }],
                         is_amount_specified: true,
                         assertion: None,
                         is_virtual: false,
+                        is_balanced_virtual: false,
+                        is_generated: false,
+                        comment: Some(String::from(" posting comment\n same comment second line")),
+                        tags: Vec::new(),
+                        position: 134..240,
                     },
                     Posting {
                         status: None,
@@ -97,9 +132,19 @@ mod tests {
                         is_amount_specified: false,
                         assertion: None,
                         is_virtual: false,
+                        is_balanced_virtual: false,
+                        is_generated: false,
+                        comment: None,
+                        tags: Vec::new(),
+                        position: 241..260,
                     }
                 ],
                 position: (0..260),
+                is_generated: false,
+                comment: Some(String::from(
+                    " transaction comment\n same comment second line"
+                )),
+                tags: Vec::new(),
             })
         );
     }
@@ -134,10 +179,16 @@ mod tests {
                             quantity: Decimal::new(1, 0),
                             commodity: String::from("$"),
                             price: None,
+                            ..Amount::default()
                         }],
                         is_amount_specified: true,
                         assertion: None,
                         is_virtual: false,
+                        is_balanced_virtual: false,
+                        is_generated: false,
+                        comment: None,
+                        tags: Vec::new(),
+                        position: 18..47,
                     },
                     Posting {
                         status: None,
@@ -149,9 +200,17 @@ mod tests {
                         is_amount_specified: false,
                         assertion: None,
                         is_virtual: false,
+                        is_balanced_virtual: false,
+                        is_generated: false,
+                        comment: None,
+                        tags: Vec::new(),
+                        position: 48..67,
                     }
                 ],
                 position: (0..67),
+                is_generated: false,
+                comment: None,
+                tags: Vec::new(),
             })
         );
     }
@@ -172,7 +231,36 @@ mod tests {
                 note: None,
                 postings: vec![],
                 position: (0..8),
+                is_generated: false,
+                comment: None,
+                tags: Vec::new(),
             })
         );
     }
+
+    #[test]
+    fn tags() {
+        let result = transaction()
+            .then_ignore(end())
+            .parse(
+                "2008/1/1 shop  ; trip: paris
+    ; receipt: yes
+    expenses:food  $1  ; type: groceries
+    assets:cash",
+            )
+            .into_result()
+            .unwrap();
+        assert_eq!(
+            result.tags,
+            vec![
+                (String::from("trip"), String::from("paris")),
+                (String::from("receipt"), String::from("yes")),
+            ]
+        );
+        assert_eq!(
+            result.postings[0].tags,
+            vec![(String::from("type"), String::from("groceries"))]
+        );
+        assert!(result.postings[1].tags.is_empty());
+    }
 }
@@ -0,0 +1,7 @@
+/// Directive of another journal format that is accepted, but has no hledger equivalent, so it
+/// has no effect.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Unsupported {
+    pub directive: String,
+    pub position: std::ops::Range<usize>,
+}
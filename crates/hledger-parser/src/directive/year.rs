@@ -33,7 +33,10 @@ mod tests {
 
     #[test]
     fn should_update_state() {
-        let mut state = State { year: 1 };
+        let mut state = State {
+            year: 1,
+            ..State::default()
+        };
         let result = year()
             .then_ignore(end())
             .parse_with_state("Y2024", &mut state)
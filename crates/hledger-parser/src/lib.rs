@@ -2,9 +2,12 @@
 //! See [hledger documentation](https://hledger.org/hledger.html)
 //! for journal format description.
 
+mod beancount;
 mod component;
 
 mod directive;
+mod options;
+mod recurrence;
 mod state;
 mod utils;
 
@@ -14,16 +17,22 @@ use chumsky::prelude::*;
 use crate::directive::directives;
 use crate::state::State;
 
+pub use crate::component::account_name::AccountName;
 pub use crate::component::amount::Amount;
 pub use crate::component::interval::Interval;
+pub use crate::component::metadata::Metadata;
 pub use crate::component::period::Period;
 pub use crate::component::price::AmountPrice;
+pub use crate::component::quantity::Style as QuantityStyle;
 pub use crate::component::query::{Amount as AmountCondition, Condition, Sign as AmountSign, Term};
 pub use crate::component::status::Status;
 pub use crate::directive::{
-    Account, Assertion, AutoPosting, AutosPostingRule, Commodity, DecimalMark, Directive, Format,
-    Include, Payee, PeriodicTransaction, Posting, Price, Tag, Transaction, Year,
+    Account, Alias, ApplyAccount, Assertion, AutoAmount, AutoAmountExpression, AutoPosting,
+    AutosPostingRule, Commodity, DecimalMark, Directive, Format, Include, Payee,
+    PeriodicTransaction, Posting, Price, Tag, Transaction, Unsupported, Year,
 };
+pub use crate::options::{Dialect, ParseOptions};
+pub use crate::recurrence::{buckets, occurrences, Buckets, Occurrences};
 
 use crate::component::query::query;
 
@@ -33,9 +42,22 @@ use crate::component::query::query;
 ///
 /// Will return a list of parsing errors if input is not a query.
 pub fn parse_query<I: AsRef<str>>(contents: I) -> Result<Vec<Term>, Vec<ParseError>> {
+    parse_query_with_options(contents, &ParseOptions::default())
+}
+
+/// Parses the given content into a hledger query, resolving relative dates against
+/// the given options.
+///
+/// # Errors
+///
+/// Will return a list of parsing errors if input is not a query.
+pub fn parse_query_with_options<I: AsRef<str>>(
+    contents: I,
+    options: &ParseOptions,
+) -> Result<Vec<Term>, Vec<ParseError>> {
     query()
         .then_ignore(end())
-        .parse_with_state(contents.as_ref(), &mut State::default())
+        .parse_with_state(contents.as_ref(), &mut State::from(options))
         .into_result()
         .map_err(|errors| errors.into_iter().map(ParseError::from).collect())
 }
@@ -46,7 +68,47 @@ pub fn parse_query<I: AsRef<str>>(contents: I) -> Result<Vec<Term>, Vec<ParseErr
 ///
 /// Will return a list of parsing errors if input is not a valid hledger journal.
 pub fn parse<I: AsRef<str>>(contents: I) -> Result<Vec<Directive>, Vec<ParseError>> {
+    parse_with_options(contents, &ParseOptions::default())
+}
+
+/// Parses the given content into a list of Hledger journal directives, using the given
+/// options. Contents of a path with the `.beancount` extension are parsed like
+/// [`parse_beancount`] does.
+///
+/// # Errors
+///
+/// Will return a list of parsing errors if input is not a valid hledger journal.
+pub fn parse_with_options<I: AsRef<str>>(
+    contents: I,
+    options: &ParseOptions,
+) -> Result<Vec<Directive>, Vec<ParseError>> {
+    let is_beancount = options
+        .path
+        .as_ref()
+        .and_then(|path| path.extension())
+        .is_some_and(|extension| extension == "beancount");
+    if is_beancount {
+        return parse_beancount(contents);
+    }
     directives()
+        .then_ignore(end())
+        .parse_with_state(contents.as_ref(), &mut State::from(options))
+        .into_result()
+        .map_err(|errors| errors.into_iter().map(ParseError::from).collect())
+}
+
+/// Parses the given Beancount ledger into a list of Hledger journal directives.
+///
+/// Beancount directives without an hledger equivalent, like `close` or `option`, are returned as
+/// [`Unsupported`]. `balance` and `pad` directives become transactions with balance assertions
+/// and assignments, dated the day before the balance, because Beancount checks balances at the
+/// beginning of the day.
+///
+/// # Errors
+///
+/// Will return a list of parsing errors if input is not a valid Beancount ledger.
+pub fn parse_beancount<I: AsRef<str>>(contents: I) -> Result<Vec<Directive>, Vec<ParseError>> {
+    beancount::directives()
         .then_ignore(end())
         .parse_with_state(contents.as_ref(), &mut State::default())
         .into_result()
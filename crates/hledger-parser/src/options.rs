@@ -0,0 +1,177 @@
+use std::time::SystemTime;
+
+use chrono::Datelike;
+
+use crate::component::account_name::AccountName;
+use crate::directive::Alias;
+
+/// Journal syntax to accept.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Dialect {
+    /// hledger journal format.
+    #[default]
+    Hledger,
+    /// hledger journal format, extended with the subset of ledger-cli syntax that has an hledger
+    /// equivalent: `!include`, `bucket`, lot prices, `@@@` prices, `Payee -- note`, and automated
+    /// transactions with regular expressions or simple value expressions.
+    Ledger,
+}
+
+/// Options that control how journals and queries are parsed.
+///
+/// When a journal includes other files, hledger parses them with the state of the parent file at
+/// the include directive, so the same options can be passed down to the included files.
+///
+/// Defaults are taken from the system clock.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseOptions {
+    /// Path of the file being parsed, if any. Like hledger, the format of the file is picked from
+    /// its extension: `.beancount` files are parsed as Beancount ledgers, others as journals.
+    pub path: Option<std::path::PathBuf>,
+    /// Date that relative dates, like `today` or `last month`, are resolved against.
+    pub today: chrono::NaiveDate,
+    /// Year of dates that do not specify it, until a year directive changes it.
+    pub year: i32,
+    /// Decimal mark of amounts, until a decimal mark directive changes it. When not set, it is
+    /// inferred from every amount.
+    pub decimal_mark: Option<char>,
+    /// Account aliases in the order of definition.
+    pub aliases: Vec<Alias>,
+    /// Parent accounts of `apply account` directives, outermost first.
+    pub apply_accounts: Vec<AccountName>,
+    /// Syntax of the journal.
+    pub dialect: Dialect,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        let current_time = SystemTime::now();
+        let datetime: chrono::DateTime<chrono::Local> = current_time.into();
+        Self {
+            path: None,
+            today: datetime.date_naive(),
+            year: datetime.year(),
+            decimal_mark: None,
+            aliases: Vec::new(),
+            apply_accounts: Vec::new(),
+            dialect: Dialect::default(),
+        }
+    }
+}
+
+impl ParseOptions {
+    /// Options with defaults taken from the system clock.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the path of the file being parsed.
+    #[must_use]
+    pub fn with_path<P: AsRef<std::path::Path>>(mut self, path: P) -> Self {
+        self.path = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Sets the date that relative dates are resolved against.
+    #[must_use]
+    pub fn with_today(mut self, today: chrono::NaiveDate) -> Self {
+        self.today = today;
+        self
+    }
+
+    /// Sets the year of dates that do not specify it.
+    #[must_use]
+    pub fn with_year(mut self, year: i32) -> Self {
+        self.year = year;
+        self
+    }
+
+    /// Sets the decimal mark of amounts instead of inferring it.
+    #[must_use]
+    pub fn with_decimal_mark(mut self, decimal_mark: char) -> Self {
+        self.decimal_mark = Some(decimal_mark);
+        self
+    }
+
+    /// Adds an account alias after the ones already defined.
+    #[must_use]
+    pub fn with_alias(mut self, alias: Alias) -> Self {
+        self.aliases.push(alias);
+        self
+    }
+
+    /// Adds a parent account inside the ones already applied.
+    #[must_use]
+    pub fn with_apply_account(mut self, account: AccountName) -> Self {
+        self.apply_accounts.push(account);
+        self
+    }
+
+    /// Sets the syntax of the journal.
+    #[must_use]
+    pub fn with_dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal::Decimal;
+
+    use super::*;
+    use crate::Directive;
+
+    #[test]
+    fn inherited_state() {
+        let options = ParseOptions::new()
+            .with_year(2020)
+            .with_decimal_mark(',')
+            .with_alias(Alias::Simple {
+                from: String::from("personal:checking"),
+                to: String::from("assets:checking"),
+            })
+            .with_apply_account(AccountName::from_parts(&[String::from("personal")]));
+        let directives = crate::parse_with_options(
+            "01/02 test
+    checking  1,5
+    income",
+            &options,
+        )
+        .unwrap();
+        let Some(Directive::Transaction(transaction)) = directives.first() else {
+            panic!("expected transaction");
+        };
+        assert_eq!(
+            transaction.date,
+            chrono::NaiveDate::from_ymd_opt(2020, 1, 2).unwrap()
+        );
+        assert_eq!(
+            transaction.postings[0].account_name.to_string(),
+            "assets:checking"
+        );
+        assert_eq!(
+            transaction.postings[1].account_name.to_string(),
+            "personal:income"
+        );
+        assert_eq!(
+            transaction.postings[0].amount[0].quantity,
+            Decimal::new(15, 1)
+        );
+    }
+
+    #[test]
+    fn beancount_path() {
+        let options = ParseOptions::new().with_path("main.beancount");
+        let directives = crate::parse_with_options(
+            "2024-01-02 * \"Shop\"\n  Assets:Cash  -1 USD\n  Expenses:Food\n",
+            &options,
+        )
+        .unwrap();
+        let Some(Directive::Transaction(transaction)) = directives.first() else {
+            panic!("expected transaction");
+        };
+        assert_eq!(transaction.payee, "Shop");
+    }
+}
@@ -0,0 +1,373 @@
+use chrono::Datelike;
+
+use crate::component::interval::Interval;
+use crate::component::period::Period;
+
+impl Interval {
+    /// Returns the start of the interval that contains the given date, i.e. the natural
+    /// boundary on or before it: monday for weeks, first day of a month, quarter or year.
+    #[allow(clippy::missing_panics_doc)]
+    #[must_use]
+    pub fn start_of(&self, date: chrono::NaiveDate) -> chrono::NaiveDate {
+        match self {
+            Interval::NthDay(_) => date,
+            Interval::NthWeek(_) => date.week(chrono::Weekday::Mon).first_day(),
+            Interval::Weekday(weekday) => date.week(*weekday).first_day(),
+            Interval::NthMonth(_) => date.with_day(1).expect("first day of month is valid"),
+            Interval::NthQuarter(_) => {
+                let month = (date.month0() / 3) * 3 + 1;
+                chrono::NaiveDate::from_ymd_opt(date.year(), month, 1)
+                    .expect("first day of quarter is valid")
+            }
+            Interval::NthYear(_) => chrono::NaiveDate::from_ymd_opt(date.year(), 1, 1)
+                .expect("first day of year is valid"),
+        }
+    }
+
+    /// Returns the n-th occurrence counting from `start`.
+    ///
+    /// Every occurrence is computed from `start` directly, so when `start` is the 31st,
+    /// months without that day are clipped to their last day without affecting the
+    /// following months.
+    #[must_use]
+    pub fn nth(&self, start: chrono::NaiveDate, n: u32) -> Option<chrono::NaiveDate> {
+        match self {
+            Interval::NthDay(days) => {
+                start.checked_add_days(chrono::Days::new(u64::from(*days) * u64::from(n)))
+            }
+            Interval::NthWeek(weeks) => {
+                start.checked_add_days(chrono::Days::new(u64::from(*weeks) * u64::from(n) * 7))
+            }
+            Interval::Weekday(_) => start.checked_add_days(chrono::Days::new(u64::from(n) * 7)),
+            Interval::NthMonth(months) => {
+                start.checked_add_months(chrono::Months::new(months.checked_mul(n)?))
+            }
+            Interval::NthQuarter(quarters) => start.checked_add_months(chrono::Months::new(
+                quarters.checked_mul(n)?.checked_mul(3)?,
+            )),
+            Interval::NthYear(years) => start
+                .checked_add_months(chrono::Months::new(years.checked_mul(n)?.checked_mul(12)?)),
+        }
+    }
+}
+
+/// Iterator over the dates described by an interval within a window.
+#[derive(Debug, Clone)]
+pub struct Occurrences {
+    interval: Interval,
+    start: Option<chrono::NaiveDate>,
+    begin: Option<chrono::NaiveDate>,
+    end: Option<chrono::NaiveDate>,
+    n: u32,
+}
+
+impl Iterator for Occurrences {
+    type Item = chrono::NaiveDate;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = self.start?;
+        loop {
+            let date = self.interval.nth(start, self.n)?;
+            self.n = self.n.checked_add(1)?;
+            // intervals of zero days, weeks, months... occur once, they never move forward
+            if self.end.is_some_and(|end| date >= end) || (self.n > 1 && date <= start) {
+                self.start = None;
+                return None;
+            }
+            match self.begin {
+                Some(begin) if date < begin => {}
+                _ => return Some(date),
+            }
+        }
+    }
+}
+
+/// Iterator over the report periods an interval splits a window into.
+#[derive(Debug, Clone)]
+pub struct Buckets {
+    occurrences: Occurrences,
+    end: Option<chrono::NaiveDate>,
+    current: Option<chrono::NaiveDate>,
+}
+
+impl Iterator for Buckets {
+    type Item = Period;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let begin = self.current.take()?;
+        if self.end.is_some_and(|end| begin >= end) {
+            return None;
+        }
+        let end = self.occurrences.next();
+        self.current = end;
+        Some(Period {
+            begin: Some(begin),
+            end,
+        })
+    }
+}
+
+/// Returns dates on which a periodic rule with the given interval and period occurs
+/// within the window.
+///
+/// When the period has a start date, occurrences are counted from it exactly, except for
+/// weekday intervals, which start on the first such weekday from it. Otherwise they fall on the natural interval boundaries, starting from the boundary
+/// that contains the window start. Dates outside of both the period and the window
+/// are skipped. When neither of them has a start date, no dates are produced, and when
+/// neither has an end date, the iterator is infinite.
+#[must_use]
+pub fn occurrences(interval: &Interval, period: Option<&Period>, window: &Period) -> Occurrences {
+    let period_begin = period.and_then(|period| period.begin);
+    let period_end = period.and_then(|period| period.end);
+    let start = match (interval, period_begin) {
+        (Interval::Weekday(_), Some(begin)) => Some(interval.start_of(begin)),
+        (_, Some(begin)) => Some(begin),
+        (_, None) => window.begin.map(|begin| interval.start_of(begin)),
+    };
+    Occurrences {
+        interval: interval.clone(),
+        start,
+        begin: max_date(period_begin, window.begin),
+        end: min_date(period_end, window.end),
+        n: 0,
+    }
+}
+
+/// Returns report periods the window is split into by the given interval.
+///
+/// Like hledger does for reports, the first period starts on the interval boundary that
+/// contains the start date, and the last one is extended to the end of its interval, so
+/// every period covers a whole interval.
+#[must_use]
+pub fn buckets(interval: &Interval, period: Option<&Period>, window: &Period) -> Buckets {
+    let begin = max_date(period.and_then(|period| period.begin), window.begin);
+    let end = min_date(period.and_then(|period| period.end), window.end);
+    let start = begin.map(|begin| interval.start_of(begin));
+    let mut occurrences = Occurrences {
+        interval: interval.clone(),
+        start,
+        begin: None,
+        end: None,
+        n: 0,
+    };
+    let current = occurrences.next();
+    Buckets {
+        occurrences,
+        end,
+        current,
+    }
+}
+
+fn max_date(
+    a: Option<chrono::NaiveDate>,
+    b: Option<chrono::NaiveDate>,
+) -> Option<chrono::NaiveDate> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.max(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+fn min_date(
+    a: Option<chrono::NaiveDate>,
+    b: Option<chrono::NaiveDate>,
+) -> Option<chrono::NaiveDate> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> chrono::NaiveDate {
+        chrono::NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn window(begin: chrono::NaiveDate, end: chrono::NaiveDate) -> Period {
+        Period {
+            begin: Some(begin),
+            end: Some(end),
+        }
+    }
+
+    #[test]
+    fn monthly_aligned() {
+        let result = occurrences(
+            &Interval::NthMonth(1),
+            None,
+            &window(date(2024, 1, 15), date(2024, 4, 1)),
+        )
+        .collect::<Vec<_>>();
+        assert_eq!(result, vec![date(2024, 2, 1), date(2024, 3, 1)]);
+    }
+
+    #[test]
+    fn monthly_from_start_date() {
+        let period = Period {
+            begin: Some(date(2023, 4, 15)),
+            end: Some(date(2023, 6, 16)),
+        };
+        let result = occurrences(
+            &Interval::NthMonth(1),
+            Some(&period),
+            &window(date(2023, 1, 1), date(2024, 1, 1)),
+        )
+        .collect::<Vec<_>>();
+        assert_eq!(
+            result,
+            vec![date(2023, 4, 15), date(2023, 5, 15), date(2023, 6, 15)]
+        );
+    }
+
+    #[test]
+    fn month_end_clipping() {
+        let period = Period {
+            begin: Some(date(2024, 1, 31)),
+            end: None,
+        };
+        let result = occurrences(
+            &Interval::NthMonth(1),
+            Some(&period),
+            &window(date(2024, 1, 1), date(2024, 5, 1)),
+        )
+        .collect::<Vec<_>>();
+        assert_eq!(
+            result,
+            vec![
+                date(2024, 1, 31),
+                date(2024, 2, 29),
+                date(2024, 3, 31),
+                date(2024, 4, 30),
+            ]
+        );
+    }
+
+    #[test]
+    fn weekday() {
+        let result = occurrences(
+            &Interval::Weekday(chrono::Weekday::Tue),
+            None,
+            &window(date(2024, 10, 1), date(2024, 10, 20)),
+        )
+        .collect::<Vec<_>>();
+        assert_eq!(
+            result,
+            vec![date(2024, 10, 1), date(2024, 10, 8), date(2024, 10, 15)]
+        );
+    }
+
+    #[test]
+    fn weekday_from_start_date() {
+        // 2024-01-01 is a monday
+        let period = Period {
+            begin: Some(date(2024, 1, 1)),
+            end: None,
+        };
+        let result = occurrences(
+            &Interval::Weekday(chrono::Weekday::Tue),
+            Some(&period),
+            &window(date(2023, 12, 1), date(2024, 1, 20)),
+        )
+        .collect::<Vec<_>>();
+        assert_eq!(
+            result,
+            vec![date(2024, 1, 2), date(2024, 1, 9), date(2024, 1, 16)]
+        );
+    }
+
+    #[test]
+    fn unbounded() {
+        let result = occurrences(
+            &Interval::NthYear(2),
+            None,
+            &Period {
+                begin: Some(date(2020, 6, 1)),
+                end: None,
+            },
+        )
+        .take(2)
+        .collect::<Vec<_>>();
+        assert_eq!(result, vec![date(2022, 1, 1), date(2024, 1, 1)]);
+    }
+
+    #[test]
+    fn no_start() {
+        let result = occurrences(
+            &Interval::NthDay(1),
+            None,
+            &Period {
+                begin: None,
+                end: Some(date(2020, 6, 1)),
+            },
+        )
+        .next();
+        assert_eq!(result, None);
+    }
+
+    #[test]
+    fn zero_interval() {
+        let period = Period {
+            begin: Some(date(2024, 1, 10)),
+            end: None,
+        };
+        for interval in [
+            Interval::NthDay(0),
+            Interval::NthWeek(0),
+            Interval::NthMonth(0),
+            Interval::NthQuarter(0),
+            Interval::NthYear(0),
+        ] {
+            let result = occurrences(
+                &interval,
+                Some(&period),
+                &Period {
+                    begin: None,
+                    end: None,
+                },
+            )
+            .take(3)
+            .collect::<Vec<_>>();
+            assert_eq!(result, vec![date(2024, 1, 10)]);
+            let result = buckets(&interval, None, &period).take(3).count();
+            assert_eq!(result, 1);
+        }
+    }
+
+    #[test]
+    fn quarterly_buckets() {
+        let result = buckets(
+            &Interval::NthQuarter(1),
+            None,
+            &window(date(2024, 2, 10), date(2024, 8, 1)),
+        )
+        .collect::<Vec<_>>();
+        assert_eq!(
+            result,
+            vec![
+                window(date(2024, 1, 1), date(2024, 4, 1)),
+                window(date(2024, 4, 1), date(2024, 7, 1)),
+                window(date(2024, 7, 1), date(2024, 10, 1)),
+            ]
+        );
+    }
+
+    #[test]
+    fn weekly_buckets() {
+        let result = buckets(
+            &Interval::NthWeek(1),
+            None,
+            &window(date(2024, 10, 2), date(2024, 10, 14)),
+        )
+        .collect::<Vec<_>>();
+        assert_eq!(
+            result,
+            vec![
+                window(date(2024, 9, 30), date(2024, 10, 7)),
+                window(date(2024, 10, 7), date(2024, 10, 14)),
+            ]
+        );
+    }
+}
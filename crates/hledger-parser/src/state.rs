@@ -1,17 +1,43 @@
-use std::time::SystemTime;
-
-use chrono::Datelike;
+use crate::component::account_name::AccountName;
+use crate::directive::{rewrite, Alias};
+use crate::options::{Dialect, ParseOptions};
 
 pub struct State {
     pub year: i32,
+    pub today: chrono::NaiveDate,
+    pub decimal_mark: Option<char>,
+    pub aliases: Vec<Alias>,
+    /// Parent accounts of the enclosing `apply account` directives, outermost first.
+    pub apply_accounts: Vec<AccountName>,
+    pub dialect: Dialect,
+    /// Account that balances transactions with a single posting, set by ledger's `bucket`.
+    pub bucket: Option<AccountName>,
 }
 
 impl Default for State {
     fn default() -> Self {
-        let current_time = SystemTime::now();
-        let datetime: chrono::DateTime<chrono::Local> = current_time.into();
+        Self::from(&ParseOptions::default())
+    }
+}
+
+impl From<&ParseOptions> for State {
+    fn from(options: &ParseOptions) -> Self {
         Self {
-            year: datetime.year(),
+            year: options.year,
+            today: options.today,
+            decimal_mark: options.decimal_mark,
+            aliases: options.aliases.clone(),
+            apply_accounts: options.apply_accounts.clone(),
+            dialect: options.dialect,
+            bucket: None,
         }
     }
 }
+
+impl State {
+    /// Account name as written in the journal, under the applied parent accounts and rewritten
+    /// by aliases.
+    pub fn account_name(&self, account_name: AccountName) -> AccountName {
+        rewrite(&self.aliases, account_name.under(&self.apply_accounts))
+    }
+}
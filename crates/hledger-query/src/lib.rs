@@ -1,6 +1,7 @@
-pub use hledger_parser::{ParseError, Posting, Transaction};
+pub use hledger_parser::{ParseError, ParseOptions, Posting, Transaction};
 
 #[derive(Default)]
+#[allow(clippy::struct_field_names)]
 pub struct Query {
     description_filters: Vec<Filter>,
     account_filters: Vec<Filter>,
@@ -19,7 +20,19 @@ pub enum Error {
 impl Query {
     #[allow(clippy::missing_errors_doc)]
     pub fn parse(query: &str) -> Result<Query, Error> {
-        let terms = hledger_parser::parse_query(query).map_err(Error::Parse)?;
+        Self::parse_with_options(query, &ParseOptions::default())
+    }
+
+    #[allow(clippy::missing_errors_doc)]
+    pub fn parse_with_options(query: &str, options: &ParseOptions) -> Result<Query, Error> {
+        let terms =
+            hledger_parser::parse_query_with_options(query, options).map_err(Error::Parse)?;
+        Self::from_terms(&terms)
+    }
+
+    /// Builds a query from parsed terms, like the query of an auto posting rule.
+    #[allow(clippy::missing_errors_doc)]
+    pub fn from_terms(terms: &[hledger_parser::Term]) -> Result<Query, Error> {
         let description_filters = terms
             .iter()
             .filter(|term| matches!(term.condition, hledger_parser::Condition::Description(_)))
@@ -83,6 +96,12 @@ impl Query {
         })
     }
 
+    /// Returns whether the posting of the transaction matches the query.
+    #[must_use]
+    pub fn matches(&self, tx: &Transaction, posting: &Posting) -> bool {
+        self.filter_transaction(tx) && self.filter_posting(posting)
+    }
+
     fn filter_posting(&self, posting: &Posting) -> bool {
         (self.account_filters.is_empty()
             || self